@@ -1,466 +1,6488 @@
 #![allow(non_camel_case_types)]
+#![allow(non_local_definitions)] // emitted by the `serde_derive` impls.
+#![allow(deprecated)]            // `arg_enum!` still pulls in `AsciiExt`.
+#![allow(clippy::needless_return)]
 
 use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::fs;
 use std::fs::OpenOptions;
 use std::env;
 use std::str::FromStr;
 use std::fmt;
+use std::ops;
+use std::ptr;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::iter::Sum;
+use std::sync::OnceLock;
 
-#[macro_use] extern crate structopt;
+extern crate structopt;
 #[macro_use] extern crate clap;
 #[macro_use] extern crate serde_derive;
 
 extern crate serde;
-extern crate serde_json;
+#[macro_use] extern crate serde_json;
+extern crate chrono;
 
 use structopt::StructOpt;
+use structopt::clap::Shell;
+use chrono::{DateTime, Local, Datelike, Months, NaiveDate};
 
 #[derive(StructOpt)]
 /// personal finance reporter.
+struct Opt
+{
+    #[structopt(long = "data-dir", parse(from_os_str), raw(global = "true"))]
+    /// keep pfr's files in this directory, instead of `$PFR_HOME` or `~/.pfr/`
+    data_dir: Option<PathBuf>,
+
+    #[structopt(long = "locale", raw(global = "true"))]
+    /// format amounts and dates for this locale, e.g. en-US, en-GB, de-DE or fr-FR
+    locale: Option<String>,
+
+    #[structopt(long = "json", raw(global = "true"))]
+    /// print `list`, `report` and errors as JSON, for scripts; also set by PFR_OUTPUT=json
+    json: bool,
+
+    #[structopt(subcommand)]
+    command: Commands,
+}
+
+
+#[derive(StructOpt)]
 enum Commands
 {
     /// init the list of entries.
-    init,
+    init
+    {
+        #[structopt(long = "force")]
+        /// start a new, empty ledger even if the current one has entries in it
+        force: bool,
+    },
 
-    /// add a new entry.
+    /// add a new entry; with nothing after `add`, asks for each part of it in turn.
     add(Transaction),
 
-    /// remove an existing entry.
+    #[structopt(name = "add-wizard", raw(setting = "structopt::clap::AppSettings::Hidden"))]
+    /// what a bare `pfr add` runs, from a terminal.
+    add_wizard,
+
+    /// change an existing entry.
+    edit(EditCommand),
+
+    /// show everything about an entry, given by id or name.
+    show { name: String },
+
+    /// leave an entry (by id or name) out of everything until it is resumed.
+    pause { name: String },
+
+    /// bring back an entry that was paused.
+    resume { name: String },
+
+    /// keep a file or URL with an entry; files are copied into ~/.pfr/attachments.
+    attach
+    {
+        /// the entry, by id or name
+        name: String,
+
+        /// the file or URL
+        path: String,
+    },
+
+    /// open the files and URLs attached to an entry.
+    open { name: String },
+
+    /// remove an existing entry; it is moved to the trash.
     rm(RmCommand),
 
     /// list the current entries.
-    list,
+    list(ListCommand),
 
     /// generate a report for the month
-    report,
+    report(ReportCommand),
+
+    /// print only the projected net for the month.
+    total
+    {
+        #[structopt(long = "per")]
+        /// show the net per this period instead of per month, e.g. weekly or 3m
+        per: Option<Period>,
+    },
 
     /// save the current ledger using `name`; can be loaded again with `load name`.
     save { name: String },
 
     /// loads the ledger that was saved by `save name`.
-    load{ name: String },
+    load
+    {
+        name: String,
+
+        #[structopt(long = "no-backup-before")]
+        /// don't back up the current ledger before replacing it
+        no_backup_before: bool,
+    },
 
     /// backs up the current ledger
     backup,
 
     /// restores the backup
-    restore,
+    restore
+    {
+        #[structopt(long = "no-backup-before")]
+        /// don't back up the current ledger before replacing it
+        no_backup_before: bool,
+    },
+
+    /// compares the monthly expenses by category of two saved ledgers.
+    compare { a: String, b: String },
+
+    /// shows the entries added, removed and changed from saved ledger `a` to `b`, or to the current ledger.
+    diff { a: String, b: Option<String> },
+
+    /// suggests how to split your income between buckets such as needs, wants and savings.
+    allocate
+    {
+        #[structopt(long = "rule", default_value = "50-30-20")]
+        /// the percentage of income for each bucket, e.g. 60-20-20
+        rule: AllocationRule,
+    },
+
+    /// searches every saved ledger for transactions whose name, category, account, payee, note or tags contain `pattern`.
+    search
+    {
+        /// the text to look for, ignoring case; or, with `*` or `?` in it, a glob that a whole field has to match
+        pattern: String,
+    },
+
+    #[structopt(raw(alias = r#""forecast""#))]
+    /// projects your balance month-by-month (also `forecast`).
+    project(ProjectCommand),
+
+    /// view, restore or permanently delete removed entries.
+    trash(TrashCommand),
+
+    /// writes the current entries out for use in a spreadsheet.
+    export
+    {
+        #[structopt(long = "format", default_value = "csv", raw(possible_values = "&ExportFormat::variants()", case_insensitive = "true"))]
+        /// the format to write
+        format: ExportFormat,
+    },
+
+    /// a one-screen summary of the current ledger.
+    status,
+
+    /// adds up the account balances and debts in the config, now and a while from now.
+    networth
+    {
+        #[structopt(long = "months", default_value = "12")]
+        /// how many months ahead to project the net worth, at the current monthly net
+        months: u32,
+    },
+
+    /// lists the recurring expenses that are subscriptions, costliest first.
+    subscriptions
+    {
+        #[structopt(long = "category")]
+        /// the categories or tags that subscriptions are under; subscriptions, subscription and subs by default
+        category: Vec<String>,
+    },
+
+    /// renames a category on every transaction that has it.
+    recategorize
+    {
+        #[structopt(long = "from")]
+        /// the category to rename
+        from: String,
+
+        #[structopt(long = "to", raw(required_unless = r#""clear""#, conflicts_with = r#""clear""#))]
+        /// the new name for the category
+        to: Option<String>,
+
+        #[structopt(long = "clear")]
+        /// remove the category from those transactions instead
+        clear: bool,
+    },
+
+    /// counts and totals up the transactions in each category, account, frequency or type.
+    stats
+    {
+        #[structopt(long = "by", default_value = "category", raw(possible_values = "&GroupBy::variants()", case_insensitive = "true"))]
+        /// what to group the transactions by
+        by: GroupBy,
+    },
+
+    /// lists groups of transactions that look like duplicates of each other.
+    dupes,
+
+    /// shows what the report would look like with some changes, without making them.
+    whatif(WhatIfCommand),
+
+    /// checks the current ledger against a bank statement for one month.
+    audit(AuditCommand),
+
+    /// lists the monthly expenses that are due soon.
+    upcoming
+    {
+        #[structopt(long = "within", default_value = "7")]
+        /// how many days ahead to look
+        within: i64,
+    },
+
+    /// shows how the current ledger has changed over time.
+    history
+    {
+        #[structopt(long = "limit")]
+        /// show only the most recent entries
+        limit: Option<usize>,
+
+        #[structopt(long = "ledger")]
+        /// show the ledger as it was after the entry with this number
+        ledger: Option<usize>,
+    },
+
+    /// puts the current ledger back as it was before the last change; again to go further back.
+    undo,
+
+    /// checks that pfr's files are in order.
+    doctor,
+
+    /// prints a JSON Schema describing the ledger file.
+    schema
+    {
+        #[structopt(long = "example")]
+        /// print an example transaction, with every field filled in, instead
+        example: bool,
+    },
+
+    /// prints the path of the current ledger's file, e.g. for `git add $(pfr path)`.
+    path
+    {
+        #[structopt(long = "ledger")]
+        /// print the path of this saved ledger instead
+        ledger: Option<String>,
+    },
+
+    /// prints a script that completes pfr's commands in bash, zsh, fish or powershell.
+    completions
+    {
+        #[structopt(raw(possible_values = "&Shell::variants()", case_insensitive = "true"))]
+        /// the shell to complete in
+        shell: Shell,
+    },
+
+    #[structopt(name = "complete-names", raw(setting = "structopt::clap::AppSettings::Hidden"))]
+    /// what the completion scripts run to list the names to complete.
+    complete_names
+    {
+        #[structopt(raw(possible_values = "&NameKind::variants()"))]
+        kind: NameKind,
+    },
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy)]
+    /// The names that the completion scripts complete.
+    enum NameKind
+    {
+        entries,
+        ledgers
+    }
+}
+
+
+#[derive(StructOpt)]
+enum TrashCommand
+{
+    /// list the entries in the trash.
+    list,
+
+    /// move an entry (by id or name) from the trash back into the current ledger.
+    restore { name: String },
+
+    /// permanently delete everything in the trash.
+    empty,
 }
 
 
-#[derive(StructOpt, Serialize, Deserialize)]
+#[derive(StructOpt, Clone, PartialEq, Serialize, Deserialize)]
 struct Transaction
 {
     #[structopt(raw(possible_values = "&AddType::variants()", case_insensitive = "true"))]
-    /// is this transaction an income or an expense?
+    /// is this transaction an income, an expense, or a transfer between accounts?
     add_type: AddType,
 
     #[structopt(raw(possible_values = "&Frequency::variants()", case_insensitive = "true"))]
     /// how often does this transaction happen?
     freq: Frequency,
 
+    #[structopt(long = "every", parse(try_from_str = "parse_every"))]
+    /// happen every N of the frequency's periods instead of every one, e.g. `weekly --every 6` for every 6 weeks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    every: Option<u32>,
+
     /// the name of the transaction.
     name: String,
 
-    /// the amount for this transaction.
-    amount: Money,
+    /// the amount for this transaction, or (for expenses and transfers) a percentage of the total income, like 15%
+    amount: Amount,
 
     #[structopt(long = "category")]
-    /// (for expenses) set the category for this transaction
+    /// set the category for this transaction; for incomes, where it comes from, like salary or dividends
     category: Option<String>,
 
-    #[structopt(long = "account")]
-    /// (for expenses) set the account that this expense comes from
+    #[structopt(long = "split", raw(number_of_values = "1"))]
+    /// (for expenses) put a share of the amount in another category, as category:percent,
+    /// e.g. household:30; can be given more than once, and the rest stays in --category
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    splits: Vec<Split>,
+
+    #[structopt(long = "account", raw(alias = r#""from""#))]
+    /// set the account that this expense comes from, or that this income is paid into; for transfers (also --from), the account the money leaves
     account: Option<String>,
+
+    #[structopt(long = "to")]
+    /// (for transfers) the account the money goes into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to_account: Option<String>,
+
+    #[structopt(long = "payee")]
+    /// who the money goes to (or comes from), like the landlord or a supermarket chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payee: Option<String>,
+
+    #[structopt(long = "skip-month", parse(try_from_str = "parse_month"))]
+    /// a month (1-12) in which this transaction doesn't happen; can be given more than once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skip_months: Vec<u8>,
+
+    #[structopt(long = "share")]
+    /// (for shared expenses) the percentage of it that is yours, e.g. 50 for rent split with a flatmate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    share: Option<f64>,
+
+    #[structopt(long = "discretionary")]
+    /// (for expenses) it could be cut if need be, like eating out, rather than essential
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    discretionary: bool,
+
+    #[structopt(long = "gross")]
+    /// (for incomes) the amount is before tax; the tax_rate from the config is taken off it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    gross: bool,
+
+    #[structopt(long = "estimated")]
+    /// the amount is an estimate, rather than a fixed amount
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    estimated: bool,
+
+    #[structopt(long = "min")]
+    /// for amounts that vary, the least it could be; the amount itself is what you expect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<Money>,
+
+    #[structopt(long = "max")]
+    /// for amounts that vary, the most it could be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<Money>,
+
+    #[structopt(long = "due-day", parse(try_from_str = "parse_day"))]
+    /// (for monthly transactions) the day of the month (1-31) that it is due on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due_day: Option<u8>,
+
+    #[structopt(long = "start-date", parse(try_from_str = "parse_date"), raw(alias = r#""on""#))]
+    /// the date (YYYY-MM-DD) this transaction starts; it is left out of months before this.
+    /// For one-offs (also --on), the date it happens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_date: Option<NaiveDate>,
+
+    #[structopt(long = "until", parse(try_from_str = "parse_date"))]
+    /// the date (YYYY-MM-DD) this transaction ends; it is left out of months after this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end_date: Option<NaiveDate>,
+
+    #[structopt(long = "tag", raw(number_of_values = "1"))]
+    /// a tag for this transaction, such as "fixed" or "household"; can be given more than once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
+    #[structopt(long = "note")]
+    /// a note to keep with this transaction, like "price goes up in March"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+
+    #[structopt(long = "attach", raw(number_of_values = "1"))]
+    /// a file (copied into ~/.pfr/attachments) or URL to keep with this transaction, like a contract; can be given more than once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<String>,
+
+    #[structopt(long = "paused")]
+    /// add it paused; it stays in the ledger, but is left out until `pfr resume`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    paused: bool,
 }
 
 
-#[derive(StructOpt)]
-struct RmCommand
+/// A share of an expense that goes to a category other than its own, given as
+/// `category:percent`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Split
 {
-    /// the entries to remove
-    names: Vec<String>
+    category: String,
+    percent: f64,
 }
 
 
-arg_enum!
+impl FromStr for Split
 {
-    #[derive(Debug, Serialize, Deserialize)]
-    /// Represents how often a transaction occurs.
-    enum Frequency
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
     {
-        daily,
-        workdays,
-        weekly,
-        monthly,
-        quarterly,
-        yearly
+        let (category, percent) = s.rsplit_once(':')
+            .ok_or_else(|| format!("{} should be category:percent", s))?;
+
+        let percent = match f64::from_str(percent.trim().trim_end_matches('%'))
+        {
+            Ok(p) if p > 0.0 && p <= 100.0 => p,
+            _                              => return Err(format!("{} is not a percentage; use a number above 0, up to 100", percent)),
+        };
+
+        return Ok(Split { category: category.trim().to_string(), percent });
     }
 }
 
 
-arg_enum!
+/// parses a calendar month, from 1 to 12.
+fn parse_month(s: &str) -> std::result::Result<u8, String>
 {
-    #[derive(Debug, Serialize, Deserialize)]
-    /// Represents the type of transaction
-    enum AddType
+    match u8::from_str(s)
     {
-        income,
-        expense
+        Ok(month) if (1..=12).contains(&month) => Ok(month),
+        _                                      => Err(format!("{} is not a month; use a number from 1 to 12", s)),
     }
 }
 
 
-#[derive(StructOpt, Serialize, Deserialize, Debug)]
-struct Money
+/// parses a date such as `2024-03-31`.
+fn parse_date(s: &str) -> std::result::Result<NaiveDate, String>
 {
-    cents: u64
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a date; use YYYY-MM-DD, e.g. 2024-03-31", s))
 }
 
 
-impl FromStr for Money
+/// parses how many periods a transaction repeats after; at least 1.
+fn parse_every(s: &str) -> std::result::Result<u32, String>
 {
-    type Err = std::num::ParseFloatError;
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    match u32::from_str(s)
     {
-        let float = f64::from_str(s)?;
-        return Ok(Money { cents: (float * 100.0) as u64 });
+        Ok(n) if n > 0 => Ok(n),
+        _              => Err(format!("{} is not a number of periods; use a whole number from 1 up", s)),
     }
 }
 
 
-impl fmt::Display for Money
+/// parses a day of the month, from 1 to 31.
+fn parse_day(s: &str) -> std::result::Result<u8, String>
 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    match u8::from_str(s)
     {
-        let dollars: u64 = self.cents / 100;
-        let cents: u64 = self.cents % 100;
-        return write!(f, "{:>4}.{:0>2}", dollars.to_string(), cents.to_string());
+        Ok(day) if (1..=31).contains(&day) => Ok(day),
+        _                                  => Err(format!("{} is not a day of the month; use a number from 1 to 31", s)),
     }
 }
 
 
-fn main()
+#[derive(StructOpt)]
+struct ListCommand
 {
-    let errors = match Commands::from_args()
-    {
-        Commands::init             => init(),
-        Commands::add(transaction) => add(transaction),
-        Commands::rm(transaction)  => rm(transaction),
-        Commands::list             => list(),
-        Commands::report           => report(),
-        Commands::save { name }    => save(name),
-        Commands::load { name }    => load(name),
-        Commands::backup           => backup(),
-        Commands::restore          => restore(),
-    };
+    #[structopt(long = "color", default_value = "auto", raw(possible_values = "&ColorChoice::variants()", case_insensitive = "true"))]
+    /// colour incomes green and expenses red
+    color: ColorChoice,
 
-    // report error if there was one.
-    errors.err().and_then(report_error);
-}
+    #[structopt(long = "limit")]
+    /// show at most this many entries
+    limit: Option<usize>,
 
+    #[structopt(long = "offset", default_value = "0")]
+    /// skip this many entries first
+    offset: usize,
 
-/// Result alias.
-type Result<T> = std::result::Result<T, Error>;
+    #[structopt(long = "active")]
+    /// show only the entries that happen this month; leaving out those that haven't started or have ended
+    active: bool,
 
+    #[structopt(long = "tag")]
+    /// show only the entries with this tag, ignoring case
+    tag: Option<String>,
 
-/// Error enum
-/// Encapsulates all the ways things can go wrong.
-enum Error
-{
-    WhileAttemptingToOpenDataFile(std::io::Error),
-    DuringInitialisation(std::io::Error),
-    DuringSerialisation(serde_json::Error),
-    DuringDeSerialisation(serde_json::Error),
-    CouldNotFindHomeDirectory,
-    NameIsAlreadyTaken(String),
+    #[structopt(long = "group-by", raw(possible_values = "&GroupBy::variants()", case_insensitive = "true", alias = r#""by""#))]
+    /// show the entries in sections by tag, account, category and so on (also --by)
+    group_by: Option<GroupBy>,
+
+    #[structopt(long = "type", raw(possible_values = "&AddType::variants()", case_insensitive = "true"))]
+    /// show only the incomes, expenses or transfers
+    add_type: Option<AddType>,
+
+    #[structopt(long = "category")]
+    /// show only the entries in this category, ignoring case
+    category: Option<String>,
+
+    #[structopt(long = "account")]
+    /// show only the entries from (or, for transfers, to) this account, ignoring case
+    account: Option<String>,
+
+    #[structopt(long = "freq", raw(possible_values = "&Frequency::variants()", case_insensitive = "true"))]
+    /// show only the entries that happen this often
+    freq: Option<Frequency>,
+
+    #[structopt(long = "sort", raw(possible_values = "&ListSort::variants()", case_insensitive = "true"))]
+    /// sort by name, monthly amount (biggest first), frequency (most often first) or category; by name if not given
+    sort: Option<ListSort>,
+
+    #[structopt(long = "reverse")]
+    /// sort the other way round
+    reverse: bool,
+
+    #[structopt(long = "format", default_value = "text", raw(possible_values = "&ListFormat::variants()", case_insensitive = "true"))]
+    /// write the entries as plain text, or as CSV rows for a spreadsheet
+    format: ListFormat,
 }
 
 
-/// Prints a description of an error that has occurred.
-fn report_error(e: Error) -> Option<()>
+#[derive(StructOpt)]
+struct ReportCommand
 {
-    use self::Error::*;
+    #[structopt(long = "color", default_value = "auto", raw(possible_values = "&ColorChoice::variants()", case_insensitive = "true"))]
+    /// colour positive amounts green and negative amounts red
+    color: ColorChoice,
 
-    print!("An error occurred");
+    #[structopt(long = "group-by", raw(possible_values = "&GroupBy::variants()", case_insensitive = "true", alias = r#""by""#))]
+    /// split the table into sections by account, category, payee and so on, each with a subtotal (also --by)
+    group_by: Option<GroupBy>,
 
-    match e
-    {
-        WhileAttemptingToOpenDataFile(io_e) => println!(" while attempting to open the data file: {}", io_e),
-        DuringInitialisation(e)             => println!(" while attempting to initialise: {}", e),
-        DuringSerialisation(e)              => println!(" while attempting to save to the data file: {}", e),
-        DuringDeSerialisation(e)            => println!(" while attempting to load from the data file: {}", e),
-        CouldNotFindHomeDirectory           => println!(" while attempting to find the current user's home directory; couldn't find it"),
-        NameIsAlreadyTaken(s)               => println!(": a transaction called {} is already present in the ledger", s)
-    }
+    #[structopt(long = "per")]
+    /// project everything onto this period instead of a month, e.g. weekly or 2w; `payday` is the pay cycle in the config
+    per: Option<Per>,
 
-    return None;
-}
+    #[structopt(long = "yearly", raw(conflicts_with = r#""per""#))]
+    /// project everything onto a year: 365 days, 365/7 weeks and so on
+    yearly: bool,
 
+    #[structopt(long = "period", raw(conflicts_with_all = r#"&["per", "yearly"]"#))]
+    /// project everything onto this many days, weeks, months and so on, e.g. 3m, 6w or 2y
+    period: Option<Period>,
 
-/// `Ledger`, just an alias for a hashmap.
-type Ledger = HashMap<String, Transaction>;
+    #[structopt(long = "month", parse(try_from_str = "parse_year_month"), raw(conflicts_with_all = r#"&["per", "yearly", "period"]"#))]
+    /// report on this month (YYYY-MM), counting its real days and working days
+    month: Option<NaiveDate>,
 
+    #[structopt(long = "prorate", raw(conflicts_with_all = r#"&["per", "yearly", "period"]"#))]
+    /// count only the days of the month that each transaction is active for, if it starts or ends partway through
+    prorate: bool,
 
-/// gets path for file called `name`, located in `~/.pfr/`
-fn get_path(name: &str) -> Result<PathBuf>
-{                     
-    let mut home_dir = env::home_dir().ok_or_else(|| Error::CouldNotFindHomeDirectory)?;
-    home_dir.push(".pfr/");
-    home_dir.push(name);
+    #[structopt(long = "income-per")]
+    /// project incomes onto this period; overrides --per
+    income_per: Option<Period>,
 
-    return Ok(home_dir);
-}
+    #[structopt(long = "expense-per")]
+    /// project expenses onto this period; overrides --per
+    expense_per: Option<Period>,
 
+    #[structopt(long = "format", default_value = "text", raw(possible_values = "&ReportFormat::variants()", case_insensitive = "true"))]
+    /// write the report as plain text, an HTML page, CSV rows, Markdown tables, or a Graphviz (dot) or SankeyMATIC money-flow diagram
+    format: ReportFormat,
 
-/// Saves the ledger to the pfr data file.
-fn save_ledger(name: &str, ledger: Ledger) -> Result<()>
-{
-    let ledgerfile = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(get_path(name)?)
-        .map_err(|e| Error::WhileAttemptingToOpenDataFile(e))?;
+    #[structopt(long = "template")]
+    /// fill in this template, from ~/.pfr/templates/, with the report; see the README for how they are written
+    template: Option<String>,
 
-    serde_json::to_writer_pretty(ledgerfile, &ledger)
-        .map_err(|e| Error::DuringSerialisation(e))
-}
+    #[structopt(long = "html", parse(from_os_str))]
+    /// write the report as an HTML page to this file, e.g. to share it
+    html: Option<PathBuf>,
 
+    #[structopt(long = "sort", raw(possible_values = "&ReportSort::variants()", case_insensitive = "true"))]
+    /// sort the rows by amount (biggest expense first), name or category; by name if not given
+    sort: Option<ReportSort>,
 
-/// loads ledger from file
-fn load_ledger(name: &str) -> Result<Ledger>
-{
-    let ledgerfile = OpenOptions::new()
-        .read(true)
-        .open(get_path(name)?)
-        .map_err(|e| Error::WhileAttemptingToOpenDataFile(e))?;
+    #[structopt(long = "descending")]
+    /// sort the rows the other way round
+    descending: bool,
 
-    serde_json::from_reader(ledgerfile)
-        .map_err(|e| Error::DuringDeSerialisation(e))
-}
+    #[structopt(long = "only", raw(possible_values = "&AddType::variants()", case_insensitive = "true"))]
+    /// report on only the incomes, or only the expenses
+    only: Option<AddType>,
 
+    #[structopt(long = "tag")]
+    /// report on only the transactions with this tag
+    tag: Option<String>,
 
-/// saves the ledger to the current ledgerfile.
-fn save_current_ledger(ledger: Ledger) -> Result<()>
-{
-    save_ledger(".current_data", ledger)
-}
+    #[structopt(long = "category")]
+    /// report on only the transactions in this category
+    category: Option<String>,
+
+    #[structopt(long = "account")]
+    /// report on only the transactions from (or, for transfers, to) this account
+    account: Option<String>,
 
+    #[structopt(long = "savings-goal")]
+    /// show whether the net meets this goal; defaults to `savings_goal` in the config
+    savings_goal: Option<Money>,
 
-/// loads the current ledger
-fn load_current_ledger() -> Result<Ledger>
-{
-    load_ledger(".current_data")
+    #[structopt(long = "bars", default_value = "auto", raw(possible_values = "&ColorChoice::variants()", case_insensitive = "true"))]
+    /// draw a bar for each amount in the breakdown and coverage; by default, only when writing to a terminal
+    bars: ColorChoice,
+
+    #[structopt(long = "top")]
+    /// list this many of the largest expenses; by default, 5 once there are more than that
+    top: Option<usize>,
+
+    #[structopt(long = "compact")]
+    /// leave out the table of transactions, and show only the totals
+    compact: bool,
+
+    #[structopt(long = "show-annual")]
+    /// add a column with what each row comes to over a year
+    show_annual: bool,
+
+    #[structopt(long = "round-to")]
+    /// round each amount to the nearest multiple of this, e.g. 5 or 10
+    round_to: Option<Money>,
+
+    #[structopt(long = "check")]
+    /// print only the net, and exit with status 2 if it is below the threshold
+    check: bool,
+
+    #[structopt(long = "threshold", default_value = "0", raw(allow_hyphen_values = "true"))]
+    /// the lowest acceptable net for --check
+    threshold: Money,
 }
 
 
-/// clears the current ledger
-fn init() -> Result<()>
+impl ReportCommand
 {
-    let mut home_dir = env::home_dir().ok_or_else(|| Error::CouldNotFindHomeDirectory)?;
-    home_dir.push(".pfr/");
+    /// the period that everything is projected onto, unless `--income-per`
+    /// or `--expense-per` say otherwise; a month, by default.
+    fn per(&self, config: &Config) -> Result<Period>
+    {
+        match (self.yearly, self.period, self.per)
+        {
+            (true, _, _)                      => Ok(Period::from(Frequency::yearly)),
+            (false, Some(p), _)               => Ok(p),
+            (false, None, Some(Per::payday))  => config.pay_cycle.map(Period::from).ok_or(Error::NoPayCycle),
+            (false, None, Some(Per::period(p))) => Ok(p),
+            (false, None, None)               => Ok(Period::from(Frequency::monthly)),
+        }
+    }
 
-    if !home_dir.exists()
+    /// the periods that incomes and expenses are projected onto.
+    fn pers(&self, config: &Config) -> Result<(Period, Period)>
     {
-        fs::create_dir(home_dir)
-            .map_err(|e| Error::DuringInitialisation(e))?;
+        let per = self.per(config)?;
+        let income_per = self.income_per.unwrap_or(per);
+        let expense_per = self.expense_per.unwrap_or(per);
+
+        return Ok((income_per, expense_per));
     }
 
-    save_current_ledger(Ledger::new())
-}
+    /// loads the config; with `--month`, the days and weeks in a month are
+    /// those of that month, whatever the config says.
+    fn load_config(&self) -> Result<Config>
+    {
+        let mut config = load_config()?;
 
+        if let Some(month) = self.month
+        {
+            let days: Vec<NaiveDate> = month.iter_days().take_while(|d| d.month() == month.month()).collect();
+            let workdays = days.iter().filter(|d| d.weekday().number_from_monday() <= 5).count();
 
-/// Adds a new entry to the ledger.
-/// Errors if an entry with the given name already exists.
-fn add(ac: Transaction) -> Result<()>
-{
-    let mut ledger = load_current_ledger()?;
+            config.days_per_month = Some(days.len() as f64);
+            config.weeks_per_month = Some(days.len() as f64 / 7.0);
+            config.workdays_per_month = Some(workdays as f64);
+        }
+
+        return Ok(config);
+    }
 
-    return match ledger.insert(ac.name.clone(), ac)
+    /// the day that the report is for; today, or the first of `--month`.
+    fn date(&self) -> NaiveDate
     {
-        Some(val) =>
-        {
-            let e = Error::NameIsAlreadyTaken(val.name.clone());
-            ledger.insert(val.name.clone(), val);
-            Err(e)
-        },
-        
-        None => save_current_ledger(ledger),
+        self.month.unwrap_or_else(|| Local::now().date_naive())
     }
 }
 
 
-/// Removes entries from the ledger.
-fn rm(rc: RmCommand) -> Result<()>
+#[derive(StructOpt)]
+struct ProjectCommand
 {
-    let mut ledger = load_current_ledger()?;
-    for name in rc.names
-    {
-        ledger.remove(&name);
-    }
+    #[structopt(long = "months", default_value = "12")]
+    /// how many months to project over
+    months: u32,
 
-    save_current_ledger(ledger)
+    #[structopt(long = "balance", default_value = "0")]
+    /// the balance to start with
+    balance: Money,
+
+    #[structopt(long = "inflation", default_value = "0")]
+    /// the yearly increase in expenses, as a percentage
+    inflation: f64,
+
+    #[structopt(long = "raise", default_value = "0")]
+    /// the yearly increase in income, as a percentage
+    raise: f64,
+
+    #[structopt(long = "per")]
+    /// show a row for each of these periods instead of each month, e.g. weekly or 2w
+    per: Option<Period>,
 }
 
 
-/// Lists all entries in the ledger.
-fn list() -> Result<()>
+#[derive(StructOpt)]
+struct WhatIfCommand
 {
-    let ledger = load_current_ledger()?;
+    #[structopt(long = "set", raw(number_of_values = "1"))]
+    /// pretend the transaction `name` has a different amount, as name:amount; can be given more than once
+    set: Vec<Override>,
 
-    for (_, value) in &ledger
-    {
-        println!("{: <14?}\t{: <14?}\t{: <20}\t{: <14}", value.freq, value.add_type, value.name, value.amount);
-    }
+    #[structopt(long = "remove", raw(number_of_values = "1"))]
+    /// pretend the transaction `name` isn't there; can be given more than once
+    remove: Vec<String>,
 
-    Ok(())
+    #[structopt(subcommand)]
+    then: WhatIfThen,
 }
 
 
-/// Generates a report for a month, extrapolating the values specified in the ledger.
-///
-/// The report has three sections; a table, a "breakdown", and a "coverage" section.
-///
-/// The table is simply a table displaying all the information about each transaction
-/// in the ledger, with costs projected onto one month. For example, a yearly income
-/// of $24k would be displayed as `2000.00`.
-///
-/// The breakdown shows the total expenses by category. You can specify the category
-/// of an expense using the `--category` option of `pfr add`. 
-///
-/// The coverage section shows how much money you need in each of your accounts
-/// in order to cover the months expenses. You can specify the account that each
-/// expense is drawn from using the `--account` option of `pfr add`.
-fn report() -> Result<()>
+#[derive(StructOpt)]
+enum WhatIfThen
 {
-    let ledger = load_current_ledger()?;
+    /// generate a report for the changed ledger.
+    report(ReportCommand),
+}
 
-    println!("Monthly Report\n");
-    println!("{:<20}{:<20}{:<12}{:<10}{:<8}", "INCOME", "EXPENDITURE", "VALUE", "CATEGORY", "ACCOUNT");
-    println!("-----------------------------------------------------------------------");
 
-    let mut total: i64 = 0;
-    let mut breakdown: HashMap<String, u64> = HashMap::new();
-    let mut other_expenses = 0;
+/// A new amount for a transaction, given as `name:amount`.
+struct Override
+{
+    name: String,
+    amount: Money,
+}
 
-    let mut coverage: HashMap<String, u64> = HashMap::new();
-    let mut other_alloc = 0;
 
-    for (_, transaction) in &ledger
+impl FromStr for Override
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
     {
-        let mut income = String::new();
-        let mut expend = String::new();
-        let mut amount = String::new();
-        let mut cat    = transaction.category.clone().unwrap_or(String::new());
-        let mut accnt  = transaction.account.clone().unwrap_or(String::new());
-
-        let multiplier: f32 = match transaction.freq
-        {
-            Frequency::daily     => 30.0,
-            Frequency::weekly    => 4.28, // note: extrapolating out to 30 day month means 4.28 weeks.
-            Frequency::workdays  => 21.4, // note: 4.28 weeks * 5 day weeks
-            Frequency::monthly   => 1.0,
-            Frequency::quarterly => 1.0/3.0,
-            Frequency::yearly    => 1.0/12.0,
-        };
+        let (name, amount) = s.rsplit_once(':')
+            .ok_or_else(|| format!("{} should be name:amount", s))?;
 
-        let money = Money { cents: (multiplier * transaction.amount.cents as f32) as u64 };
-        amount.push_str(&money.to_string());
+        let amount = Money::from_str(amount.trim()).map_err(|e| format!("{}: {}", amount, e))?;
 
-        match transaction.add_type
-        {
-            AddType::income =>
+        return Ok(Override { name: name.to_string(), amount });
+    }
+}
+
+
+#[derive(StructOpt)]
+struct AuditCommand
+{
+    #[structopt(long = "statement", parse(from_os_str))]
+    /// the bank statement; a CSV file with date, description and amount columns
+    statement: PathBuf,
+
+    #[structopt(long = "month", parse(try_from_str = "parse_year_month"))]
+    /// the month to check, as YYYY-MM; defaults to this month
+    month: Option<NaiveDate>,
+
+    #[structopt(long = "tolerance", default_value = "0")]
+    /// how far a statement amount can be from a transaction's and still match it
+    tolerance: Money,
+}
+
+
+/// parses a month such as `2024-03`, giving the first day of that month.
+fn parse_year_month(s: &str) -> std::result::Result<NaiveDate, String>
+{
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a month; use YYYY-MM, e.g. 2024-03", s))
+}
+
+
+#[derive(StructOpt)]
+struct EditCommand
+{
+    /// the entry to change, by id or name
+    name: String,
+
+    #[structopt(long = "amount")]
+    /// the new amount, or percentage of income
+    amount: Option<Amount>,
+
+    #[structopt(long = "min")]
+    /// the new least it could be
+    min: Option<Money>,
+
+    #[structopt(long = "max")]
+    /// the new most it could be
+    max: Option<Money>,
+
+    #[structopt(long = "freq", raw(possible_values = "&Frequency::variants()", case_insensitive = "true"))]
+    /// the new frequency
+    freq: Option<Frequency>,
+
+    #[structopt(long = "every", parse(try_from_str = "parse_every"))]
+    /// the new number of periods it repeats after (1 for every period)
+    every: Option<u32>,
+
+    #[structopt(long = "category")]
+    /// the new category
+    category: Option<String>,
+
+    #[structopt(long = "account")]
+    /// the new account
+    account: Option<String>,
+
+    #[structopt(long = "payee")]
+    /// the new payee
+    payee: Option<String>,
+
+    #[structopt(long = "note")]
+    /// the new note
+    note: Option<String>,
+}
+
+
+#[derive(StructOpt)]
+struct RmCommand
+{
+    /// the entries to remove, by id or name; a name with `*` or `?` in it is a pattern, e.g. 'sub:*'
+    names: Vec<String>,
+
+    #[structopt(long = "category")]
+    /// remove every transaction in this category
+    category: Option<String>,
+
+    #[structopt(long = "account")]
+    /// remove every transaction that uses this account
+    account: Option<String>,
+
+    #[structopt(long = "type", raw(possible_values = "&AddType::variants()", case_insensitive = "true"))]
+    /// remove every income, or every expense
+    add_type: Option<AddType>,
+
+    #[structopt(long = "dry-run")]
+    /// show what would be removed, without removing anything
+    dry_run: bool,
+}
+
+
+impl RmCommand
+{
+    /// true if any of the filters were given.
+    fn has_filter(&self) -> bool
+    {
+        self.category.is_some() || self.account.is_some() || self.add_type.is_some()
+    }
+
+    /// true if `transaction` matches every filter that was given, in any case.
+    fn matches(&self, transaction: &Transaction) -> bool
+    {
+        self.has_filter()
+            && self.category.as_ref().is_none_or(|c| transaction.category.as_ref().is_some_and(|t| same_label(t, c)))
+            && self.account.as_ref().is_none_or(|a| transaction.account.as_ref().is_some_and(|t| same_label(t, a)))
+            && self.add_type.is_none_or(|t| transaction.add_type == t)
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    /// Represents how often a transaction occurs.
+    enum Frequency
+    {
+        daily,
+        workdays,
+        weekly,
+        fortnightly,
+        monthly,
+        quarterly,
+        yearly,
+        once
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    /// Represents the type of transaction
+    enum AddType
+    {
+        income,
+        expense,
+        transfer
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy)]
+    /// When to colour the output; `auto` colours it only when writing to a terminal.
+    enum ColorChoice
+    {
+        auto,
+        always,
+        never
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    /// How to write out a report.
+    enum ReportFormat
+    {
+        text,
+        html,
+        csv,
+        markdown,
+        dot,
+        sankey
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    /// How to write out the list of entries.
+    enum ListFormat
+    {
+        text,
+        csv
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy)]
+    /// What to sort the rows of a report by.
+    enum ReportSort
+    {
+        amount,
+        name,
+        category
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy)]
+    /// What to sort the entries of a list by.
+    enum ListSort
+    {
+        name,
+        amount,
+        freq,
+        category
+    }
+}
+
+
+/// What `report --per` projects onto: a period, such as a frequency's or
+/// `3m`, or one pay period, as set by `pay_cycle` in the config.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Per
+{
+    period(Period),
+    payday,
+}
+
+
+impl FromStr for Per
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        match s.eq_ignore_ascii_case("payday")
+        {
+            true  => Ok(Per::payday),
+            false => Period::from_str(s).map(Per::period).map_err(|e| format!("{}, or payday", e)),
+        }
+    }
+}
+
+
+/// How to split up the report table, or group transactions for `stats`.
+///
+/// Written out by hand rather than with `arg_enum!`, since `type` can't be the
+/// name of a variant.
+#[derive(Debug, Clone, Copy)]
+enum GroupBy
+{
+    account,
+    category,
+    freq,
+    add_type,
+    tag,
+    payee,
+}
+
+
+impl GroupBy
+{
+    /// the values that `--group-by` and `--by` accept.
+    fn variants() -> [&'static str; 6]
+    {
+        ["account", "category", "freq", "type", "tag", "payee"]
+    }
+
+    /// the names of the groups that `transaction` belongs in; none if it
+    /// doesn't have the field, and possibly more than one for tags.
+    fn keys(self, transaction: &Transaction) -> Vec<String>
+    {
+        match self
+        {
+            GroupBy::account  => transaction.account.iter().cloned().collect(),
+            GroupBy::category => transaction.category.iter().cloned().collect(),
+            GroupBy::freq     => vec![transaction.describe_freq()],
+            GroupBy::add_type => vec![transaction.add_type.to_string()],
+            GroupBy::tag      => transaction.tags.clone(),
+            GroupBy::payee    => transaction.payee.iter().cloned().collect(),
+        }
+    }
+}
+
+
+impl FromStr for GroupBy
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        match s.to_lowercase().as_str()
+        {
+            "account"  => Ok(GroupBy::account),
+            "category" => Ok(GroupBy::category),
+            "freq"     => Ok(GroupBy::freq),
+            "type"     => Ok(GroupBy::add_type),
+            "tag"      => Ok(GroupBy::tag),
+            "payee"    => Ok(GroupBy::payee),
+            _          => Err(format!("valid values: {}", GroupBy::variants().join(", "))),
+        }
+    }
+}
+
+
+impl fmt::Display for GroupBy
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let name = match *self
+        {
+            GroupBy::account  => "account",
+            GroupBy::category => "category",
+            GroupBy::freq     => "freq",
+            GroupBy::add_type => "type",
+            GroupBy::tag      => "tag",
+            GroupBy::payee    => "payee",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+
+arg_enum!
+{
+    #[derive(Debug, Clone, Copy)]
+    /// The formats that `export` can write.
+    enum ExportFormat
+    {
+        csv,
+        tsv
+    }
+}
+
+
+impl ExportFormat
+{
+    /// Joins `fields` into a single line of output.
+    fn line(self, fields: &[String]) -> String
+    {
+        match self
+        {
+            ExportFormat::csv => fields.iter()
+                .map(|f| match f.contains([',', '"', '\n', '\r'])
+                {
+                    true  => format!("\"{}\"", f.replace('"', "\"\"")),
+                    false => f.clone(),
+                })
+                .collect::<Vec<String>>()
+                .join(","),
+
+            // there's no quoting in TSV, so stray tabs and newlines are replaced
+            // to keep every row the same shape.
+            ExportFormat::tsv => fields.iter()
+                .map(|f| f.replace(['\t', '\n', '\r'], " "))
+                .collect::<Vec<String>>()
+                .join("\t"),
+        }
+    }
+
+    /// Splits a single line of input into its fields; the reverse of `line`.
+    fn split(self, line: &str) -> Vec<String>
+    {
+        match self
+        {
+            ExportFormat::csv =>
+            {
+                let mut fields = Vec::new();
+                let mut field = String::new();
+                let mut quoted = false;
+                let mut chars = line.chars().peekable();
+
+                while let Some(c) = chars.next()
+                {
+                    match (c, quoted)
+                    {
+                        ('"', true) if chars.peek() == Some(&'"') =>
+                        {
+                            field.push('"');
+                            chars.next();
+                        },
+                        ('"', _)     => quoted = !quoted,
+                        (',', false) => fields.push(std::mem::take(&mut field)),
+                        _            => field.push(c),
+                    }
+                }
+
+                fields.push(field);
+                fields
+            },
+
+            ExportFormat::tsv => line.split('\t').map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+
+/// Colours amounts of money in the output; red if negative, green otherwise.
+struct Painter
+{
+    enabled: bool,
+}
+
+
+impl Painter
+{
+    /// with `auto`, colours a terminal unless `NO_COLOR` is set (see no-color.org).
+    fn new(choice: ColorChoice) -> Painter
+    {
+        let enabled = match choice
+        {
+            ColorChoice::auto   => std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+            ColorChoice::always => true,
+            ColorChoice::never  => false,
+        };
+
+        return Painter { enabled };
+    }
+
+    /// Colours `text` according to the sign of `money`.
+    /// Pad `text` before painting it; the escape codes would throw off the width.
+    fn paint(&self, text: &str, money: Money) -> String
+    {
+        if !self.enabled
+        {
+            return text.to_string();
+        }
+
+        let code = if money.mills < 0 { 31 } else { 32 };
+        return format!("\x1b[{}m{}\x1b[0m", code, text);
+    }
+
+    /// Makes `text` stand out, as for a total.
+    fn bold(&self, text: &str) -> String
+    {
+        match self.enabled
+        {
+            true  => format!("\x1b[1m{}\x1b[0m", text),
+            false => text.to_string(),
+        }
+    }
+
+    /// Marks `text` as a warning, such as being over budget.
+    fn warn(&self, text: &str) -> String
+    {
+        match self.enabled
+        {
+            true  => format!("\x1b[1;31m{}\x1b[0m", text),
+            false => text.to_string(),
+        }
+    }
+}
+
+
+#[derive(StructOpt, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+/// An amount of money, in mills (tenths of a cent). Negative amounts are money
+/// going out.
+///
+/// Amounts are projected in mills, so that each one is exact to a tenth of a
+/// cent; a report then rounds each projected amount to the cent before adding
+/// it up, so that its columns add up to exactly the total it prints.
+///
+/// Arithmetic saturates rather than overflowing.
+struct Money
+{
+    mills: i64
+}
+
+
+/// How `Money` is stored in the data files.
+/// Older versions of pfr stored whole cents.
+#[derive(Serialize, Deserialize)]
+struct StoredMoney
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mills: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cents: Option<i64>,
+}
+
+
+impl serde::Serialize for Money
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    {
+        StoredMoney { mills: Some(self.mills), cents: None }.serialize(serializer)
+    }
+}
+
+
+impl<'de> serde::Deserialize<'de> for Money
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error>
+    {
+        let stored = StoredMoney::deserialize(deserializer)?;
+
+        match (stored.mills, stored.cents)
+        {
+            (Some(mills), _)    => Ok(Money { mills }),
+            (None, Some(cents)) => Ok(Money { mills: cents.saturating_mul(10) }),
+            (None, None)        => Err(serde::de::Error::missing_field("mills")),
+        }
+    }
+}
+
+
+impl FromStr for Money
+{
+    type Err = std::num::ParseFloatError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        let float = f64::from_str(s)?;
+        return Ok(Money::from_dollars(float));
+    }
+}
+
+
+/// The amount of a transaction; a sum of money, or a percentage of the total
+/// income (e.g. `15%`), which is worked out again whenever it is used.
+#[derive(Clone, Copy, PartialEq)]
+enum Amount
+{
+    fixed(Money),
+    percent(f64),
+}
+
+
+/// How a percentage `Amount` is stored; fixed amounts are stored as `Money`.
+#[derive(Serialize, Deserialize)]
+struct StoredPercent
+{
+    percent: f64,
+}
+
+
+/// Either way that an `Amount` can be stored.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAmount
+{
+    percent(StoredPercent),
+    fixed(Money),
+}
+
+
+impl serde::Serialize for Amount
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    {
+        match *self
+        {
+            Amount::fixed(money)     => money.serialize(serializer),
+            Amount::percent(percent) => StoredPercent { percent }.serialize(serializer),
+        }
+    }
+}
+
+
+impl<'de> serde::Deserialize<'de> for Amount
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error>
+    {
+        match StoredAmount::deserialize(deserializer)?
+        {
+            StoredAmount::percent(stored) => Ok(Amount::percent(stored.percent)),
+            StoredAmount::fixed(money)    => Ok(Amount::fixed(money)),
+        }
+    }
+}
+
+
+impl FromStr for Amount
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        match s.trim().strip_suffix('%')
+        {
+            Some(percent) => f64::from_str(percent.trim())
+                .map(Amount::percent)
+                .map_err(|_| format!("{} is not a percentage, e.g. 15%", s)),
+
+            None => Money::from_str(s)
+                .map(Amount::fixed)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+
+impl fmt::Display for Amount
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match *self
+        {
+            Amount::fixed(money)     => write!(f, "{}", money),
+            Amount::percent(percent) => write!(f, "{:>7}", format!("{}%", percent)),
+        }
+    }
+}
+
+
+impl Amount
+{
+    /// the sum of money, unless this is a percentage.
+    fn money(self) -> Option<Money>
+    {
+        match self
+        {
+            Amount::fixed(money) => Some(money),
+            Amount::percent(_)   => None,
+        }
+    }
+
+    /// the sum of money for a period of `months` months; a percentage is
+    /// taken of `monthly_income` over that long.
+    fn resolve(self, monthly_income: Money, months: Ratio) -> Money
+    {
+        match self
+        {
+            Amount::fixed(money)     => money,
+            Amount::percent(percent) => monthly_income.times(Ratio::from_f64(percent / 100.0) * months),
+        }
+    }
+
+    /// the amount without any locale formatting, e.g. `1234.50` or `15%`.
+    fn plain(self) -> String
+    {
+        match self
+        {
+            Amount::fixed(money)     => money.plain(),
+            Amount::percent(percent) => format!("{}%", percent),
+        }
+    }
+}
+
+
+impl fmt::Display for Money
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let locale = locale();
+        let (dollars, cents) = self.dollars_and_cents();
+
+        let number = format!("{}{}{:0>2}", group_digits(dollars, locale.grouping), locale.decimal, cents);
+        let number = match locale.symbol
+        {
+            None                => number,
+            Some((symbol, true))  => format!("{}{}", symbol, number),
+            Some((symbol, false)) => format!("{} {}", number, symbol),
+        };
+
+        // pad to the width of `1234.56`, plus the symbol, so that columns line up.
+        let width = 7 + locale.symbol.map_or(0, |(symbol, before)| symbol.chars().count() + if before { 0 } else { 1 });
+
+        return match self.mills < 0
+        {
+            true  => write!(f, "({:>width$})", number, width = width),
+            false => write!(f, "{:>width$}", number, width = width),
+        }
+    }
+}
+
+
+/// How amounts and dates are written for a locale.
+struct Locale
+{
+    /// the character before the cents.
+    decimal: char,
+
+    /// the character between each group of three digits, if any.
+    grouping: Option<char>,
+
+    /// the currency symbol, and whether it goes before the amount.
+    symbol: Option<(&'static str, bool)>,
+
+    /// the `strftime` format for dates.
+    date: &'static str,
+}
+
+
+impl Locale
+{
+    /// the formatting used when no locale is given: `1234.56`, `2024-03-31`.
+    const DEFAULT: Locale = Locale { decimal: '.', grouping: None, symbol: None, date: "%Y-%m-%d" };
+
+    /// the locale for a tag such as `en-US` or `de_DE`, if it is one pfr knows.
+    fn from_tag(tag: &str) -> Option<Locale>
+    {
+        match tag.replace('_', "-").to_lowercase().as_str()
+        {
+            "en-us" => Some(Locale { decimal: '.', grouping: Some(','), symbol: Some(("$", true)),  date: "%m/%d/%Y" }),
+            "en-gb" => Some(Locale { decimal: '.', grouping: Some(','), symbol: Some(("£", true)),  date: "%d/%m/%Y" }),
+            "en-au" => Some(Locale { decimal: '.', grouping: Some(','), symbol: Some(("$", true)),  date: "%d/%m/%Y" }),
+            "en-nz" => Some(Locale { decimal: '.', grouping: Some(','), symbol: Some(("$", true)),  date: "%d/%m/%Y" }),
+            "de-de" => Some(Locale { decimal: ',', grouping: Some('.'), symbol: Some(("€", false)), date: "%d.%m.%Y" }),
+            "fr-fr" => Some(Locale { decimal: ',', grouping: Some(' '), symbol: Some(("€", false)), date: "%d/%m/%Y" }),
+            _       => None,
+        }
+    }
+}
+
+
+/// whether `--json` was given, or PFR_OUTPUT=json set.
+static JSON_OUTPUT: OnceLock<bool> = OnceLock::new();
+
+
+/// true if output should be JSON rather than text, where a command supports it.
+fn json_output() -> bool
+{
+    JSON_OUTPUT.get().cloned().unwrap_or(false)
+}
+
+
+/// the locale given with `--locale`, if there was one.
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+
+/// the locale to format amounts and dates for.
+fn locale() -> &'static Locale
+{
+    LOCALE.get().unwrap_or(&Locale::DEFAULT)
+}
+
+
+/// writes out `n`, with `separator` between each group of three digits.
+fn group_digits(n: u64, separator: Option<char>) -> String
+{
+    let digits = n.to_string();
+
+    let separator = match separator
+    {
+        Some(separator) => separator,
+        None            => return digits,
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate()
+    {
+        if i > 0 && (digits.len() - i).is_multiple_of(3)
+        {
+            grouped.push(separator);
+        }
+
+        grouped.push(digit);
+    }
+
+    return grouped;
+}
+
+
+/// formats a date for the locale.
+fn format_date(date: NaiveDate) -> String
+{
+    date.format(locale().date).to_string()
+}
+
+
+/// formats a date and time for the locale, to the minute.
+fn format_time(time: DateTime<Local>) -> String
+{
+    format!("{} {}", format_date(time.date_naive()), time.format("%H:%M"))
+}
+
+
+impl Money
+{
+    /// The amount `dollars`, to the nearest cent; an amount too big to hold
+    /// stops at the biggest there is.
+    fn from_dollars(dollars: f64) -> Money
+    {
+        Money { mills: ((dollars * 100.0).round() as i64).saturating_mul(10) }
+    }
+
+    /// Multiplies the amount by `factor`, to the nearest mill.
+    fn scaled(self, factor: f64) -> Money
+    {
+        Money { mills: (factor * self.mills as f64).round() as i64 }
+    }
+
+    /// Multiplies the amount by `ratio` in whole numbers, rounding once to the
+    /// nearest mill, with halves away from zero; an amount too big to hold
+    /// stops at the biggest there is.
+    fn times(self, ratio: Ratio) -> Money
+    {
+        let scaled = i128::from(self.mills).saturating_mul(ratio.num);
+        let rounded = scaled.abs().saturating_mul(2).saturating_add(ratio.den) / ratio.den.saturating_mul(2) * scaled.signum();
+        Money { mills: rounded.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64 }
+    }
+
+    /// Rounds the amount to the nearest multiple of `increment`, with halves
+    /// rounded up, away from zero; e.g. 47.00 to the nearest 5.00 is 45.00,
+    /// and 47.50 is 50.00, as -47.50 is -50.00.
+    fn round_to(self, increment: Money) -> Money
+    {
+        let step = increment.mills.unsigned_abs();
+        if step == 0
+        {
+            return self;
+        }
+
+        let size = (self.mills.unsigned_abs().saturating_add(step / 2) / step).saturating_mul(step);
+        let size = size.min(i64::MAX as u64) as i64;
+
+        match self.mills < 0
+        {
+            true  => Money { mills: -size },
+            false => Money { mills: size },
+        }
+    }
+
+    /// the amount in dollars, rounded to cents, as a number for JSON output.
+    fn to_json(self) -> serde_json::Value
+    {
+        json!(f64::from_str(&self.plain()).unwrap_or_default())
+    }
+
+    /// Formats the amount without padding or parentheses, e.g. `-20.00`, for
+    /// other programs to read.
+    fn plain(self) -> String
+    {
+        let sign = if self.mills < 0 { "-" } else { "" };
+        let (dollars, cents) = self.dollars_and_cents();
+        format!("{}{}.{:0>2}", sign, dollars, cents)
+    }
+
+    /// The size of the amount in whole dollars and cents, rounded to the nearest cent.
+    fn dollars_and_cents(self) -> (u64, u64)
+    {
+        let rounded: u64 = (self.mills.unsigned_abs() + 5) / 10;
+        (rounded / 100, rounded % 100)
+    }
+
+    /// Formats the amount for a table column; positive amounts are padded so
+    /// that they line up with (negative) ones.
+    fn signed(self) -> String
+    {
+        match self.mills < 0
+        {
+            true  => self.to_string(),
+            false => format!(" {} ", self),
+        }
+    }
+}
+
+
+impl ops::Add for Money
+{
+    type Output = Money;
+    fn add(self, other: Money) -> Money
+    {
+        Money { mills: self.mills.saturating_add(other.mills) }
+    }
+}
+
+
+impl ops::Sub for Money
+{
+    type Output = Money;
+    fn sub(self, other: Money) -> Money
+    {
+        Money { mills: self.mills.saturating_sub(other.mills) }
+    }
+}
+
+
+impl ops::Neg for Money
+{
+    type Output = Money;
+    fn neg(self) -> Money
+    {
+        Money { mills: self.mills.saturating_neg() }
+    }
+}
+
+
+impl ops::AddAssign for Money
+{
+    fn add_assign(&mut self, other: Money)
+    {
+        *self = *self + other;
+    }
+}
+
+
+impl ops::SubAssign for Money
+{
+    fn sub_assign(&mut self, other: Money)
+    {
+        *self = *self - other;
+    }
+}
+
+
+impl<'a> Sum<&'a Money> for Money
+{
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money
+    {
+        iter.fold(Money::default(), |acc, m| acc + *m)
+    }
+}
+
+
+/// A fraction, for projecting amounts from one frequency onto another without
+/// rounding along the way; kept in lowest terms, with the denominator above 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Ratio
+{
+    num: i128,
+    den: i128,
+}
+
+
+impl Ratio
+{
+    /// `num / den`; 0 if `den` is.
+    fn new(num: i128, den: i128) -> Ratio
+    {
+        if den == 0
+        {
+            return Ratio { num: 0, den: 1 };
+        }
+
+        let (mut a, mut b) = (num.abs(), den.abs());
+        while b != 0
+        {
+            (a, b) = (b, a % b);
+        }
+
+        let divisor = a * den.signum();
+        Ratio { num: num / divisor, den: den / divisor }
+    }
+
+    /// the whole number `n`.
+    fn whole(n: i64) -> Ratio
+    {
+        Ratio::new(i128::from(n), 1)
+    }
+
+    /// `x`, to the nearest millionth; for the numbers in the config.
+    fn from_f64(x: f64) -> Ratio
+    {
+        Ratio::new((x * 1_000_000.0).round() as i128, 1_000_000)
+    }
+}
+
+
+impl ops::Mul for Ratio
+{
+    type Output = Ratio;
+    fn mul(self, other: Ratio) -> Ratio
+    {
+        Ratio::new(self.num.saturating_mul(other.num), self.den.saturating_mul(other.den))
+    }
+}
+
+
+impl ops::Div for Ratio
+{
+    type Output = Ratio;
+    fn div(self, other: Ratio) -> Ratio
+    {
+        Ratio::new(self.num.saturating_mul(other.den), self.den.saturating_mul(other.num))
+    }
+}
+
+
+impl PartialOrd for Ratio
+{
+    fn partial_cmp(&self, other: &Ratio) -> Option<std::cmp::Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+
+impl Ord for Ratio
+{
+    fn cmp(&self, other: &Ratio) -> std::cmp::Ordering
+    {
+        self.num.saturating_mul(other.den).cmp(&other.num.saturating_mul(self.den))
+    }
+}
+
+
+/// true if `args` are for `add` with nothing after it; the command, after
+/// any global options, is `add`, and is the last argument.
+fn is_bare_add(args: &[String]) -> bool
+{
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next()
+    {
+        match arg.as_str()
+        {
+            "--data-dir" | "--locale"                                           => { rest.next(); },
+            "--json"                                                            => (),
+            _ if arg.starts_with("--data-dir=") || arg.starts_with("--locale=") => (),
+            _                                                                   => return arg == "add" && rest.next().is_none(),
+        }
+    }
+
+    return false;
+}
+
+
+fn main()
+{
+    // `add` needs its type, frequency, name and amount, which a bare `pfr add`
+    // asks for instead.
+    let mut args: Vec<String> = env::args().collect();
+    if is_bare_add(&args) && std::io::stdin().is_terminal()
+    {
+        *args.last_mut().unwrap() = "add-wizard".to_string();
+    }
+
+    let opt = Opt::from_iter(args);
+
+    if let Some(dir) = opt.data_dir
+    {
+        let _ = DATA_DIR.set(dir);
+    }
+
+    if let Some(tag) = opt.locale
+    {
+        match Locale::from_tag(&tag)
+        {
+            Some(locale) => { let _ = LOCALE.set(locale); },
+            None         => eprintln!("warning: unknown locale {}; using the default formatting", tag),
+        }
+    }
+
+    let _ = JSON_OUTPUT.set(opt.json || env::var("PFR_OUTPUT").is_ok_and(|output| output == "json"));
+
+    let errors = match opt.command
+    {
+        Commands::init { force }                   => init(force),
+        Commands::add(transaction)                 => add(transaction),
+        Commands::add_wizard                       => add_wizard(),
+        Commands::show { name }                    => show(name),
+        Commands::attach { name, path }            => attach(name, path),
+        Commands::open { name }                    => open(name),
+        Commands::pause { name }                   => set_paused(name, true),
+        Commands::resume { name }                  => set_paused(name, false),
+        Commands::edit(ec)                         => edit(ec),
+        Commands::rm(transaction)                  => rm(transaction),
+        Commands::list(lc)                         => list(lc),
+        Commands::report(rc)                       => report(rc),
+        Commands::total { per }                    => total(per),
+        Commands::save { name }                    => save(name),
+        Commands::load { name, no_backup_before }  => load(name, !no_backup_before),
+        Commands::backup                           => backup(),
+        Commands::restore { no_backup_before }     => restore(!no_backup_before),
+        Commands::compare { a, b }                 => compare(a, b),
+        Commands::diff { a, b }                    => diff(a, b),
+        Commands::allocate { rule }                => allocate(rule),
+        Commands::search { pattern }               => search(pattern),
+        Commands::project(pc)                      => project(pc),
+        Commands::trash(tc)                        => trash(tc),
+        Commands::export { format }                => export(format),
+        Commands::status                           => status(),
+        Commands::networth { months }              => networth(months),
+        Commands::subscriptions { category }       => subscriptions(category),
+        Commands::recategorize { from, to, clear } => recategorize(from, to.filter(|_| !clear)),
+        Commands::stats { by }                     => stats(by),
+        Commands::dupes                            => dupes(),
+        Commands::whatif(wc)                       => whatif(wc),
+        Commands::audit(ac)                        => audit(ac),
+        Commands::upcoming { within }              => upcoming(within),
+        Commands::history { limit, ledger }        => history(limit, ledger),
+        Commands::undo                             => undo(),
+        Commands::doctor                           => doctor(),
+        Commands::schema { example }               => schema(example),
+        Commands::path { ledger }                  => path(ledger),
+        Commands::completions { shell }            => completions(shell),
+        Commands::complete_names { kind }          => complete_names(kind),
+    };
+
+    // report error if there was one, and exit with the matching status.
+    if let Err(e) = errors
+    {
+        let code = e.exit_code();
+        report_error(e);
+        std::process::exit(code);
+    }
+}
+
+
+/// The percentage of income to put in each bucket, e.g. `50-30-20`.
+struct AllocationRule
+{
+    percentages: Vec<u32>,
+}
+
+
+impl FromStr for AllocationRule
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        let percentages = s.split('-')
+            .map(|p| u32::from_str(p.trim()).map_err(|e| format!("{}: {}", p, e)))
+            .collect::<std::result::Result<Vec<u32>, String>>()?;
+
+        let sum: u32 = percentages.iter().sum();
+        if sum != 100
+        {
+            return Err(format!("the percentages add up to {}, not 100", sum));
+        }
+
+        return Ok(AllocationRule { percentages });
+    }
+}
+
+
+/// Result alias.
+type Result<T> = std::result::Result<T, Error>;
+
+
+/// Error enum
+/// Encapsulates all the ways things can go wrong.
+#[derive(Debug)]
+enum Error
+{
+    WhileAttemptingToOpenDataFile(std::io::Error),
+    DuringInitialisation(std::io::Error),
+    DuringSerialisation(serde_json::Error),
+    DuringDeSerialisation(serde_json::Error),
+    CouldNotFindHomeDirectory,
+    WhileReadingConfig(serde_json::Error),
+    NothingToRemove,
+    NoSuchTransaction(String),
+    LedgerIsNotEmpty(usize),
+    LedgerIsUnreadable,
+    NetIsBelowThreshold,
+    UnknownField(String, String),
+    WhileReadingStatement(std::io::Error),
+    InvalidStatement(String),
+    NoSuchHistoryEntry(usize),
+    NothingToUndo,
+    ChecksFailed(usize),
+    OnceNeedsADate,
+    TransferNeedsAccounts,
+    PercentOfIncomeOnIncome,
+    AmountIsOutsideRange,
+    InvalidShare(f64),
+    WhileAttaching(std::io::Error),
+    NoAttachments(String),
+    WhileOpening(String, std::io::Error),
+    SplitsAreOver100(f64),
+    AmbiguousName(String, Vec<String>),
+    UnsupportedLedgerVersion(u64),
+    NoPayCycle,
+    WhileWritingReport(std::io::Error),
+    WhileReadingTemplate(String, String),
+    CannotProjectPer(String),
+    TooFarAhead,
+}
+
+
+impl Error
+{
+    /// The status that pfr exits with because of this error.
+    ///
+    /// 0: success.
+    /// 1: something went wrong.
+    /// 2: `report --check` found the net below the threshold.
+    fn exit_code(&self) -> i32
+    {
+        match *self
+        {
+            Error::NetIsBelowThreshold => 2,
+            _                          => 1,
+        }
+    }
+}
+
+
+/// Prints a description of an error that has occurred.
+fn report_error(e: Error)
+{
+    use self::Error::*;
+
+    if let NetIsBelowThreshold = e
+    {
+        // not really an error; `report --check` has already printed the net.
+        return;
+    }
+
+    let message = match e
+    {
+        WhileAttemptingToOpenDataFile(io_e) => format!(" while attempting to open the data file: {}", io_e),
+        DuringInitialisation(e)             => format!(" while attempting to initialise: {}", e),
+        DuringSerialisation(e)              => format!(" while attempting to save to the data file: {}", e),
+        DuringDeSerialisation(e)            => format!(" while attempting to load from the data file: {}", e),
+        CouldNotFindHomeDirectory           => " while attempting to find the current user's home directory; couldn't find it".to_string(),
+        WhileReadingConfig(e)               => format!(" while attempting to read the config file: {}", e),
+        NothingToRemove                     => ": nothing to remove; give at least one name, or a filter such as --category".to_string(),
+        NoSuchTransaction(s)                => format!(": there is no transaction called {}", s),
+        LedgerIsNotEmpty(n)                 => format!(": the current ledger is not empty ({} in it); use `pfr init --force` to replace it with an empty one", n),
+        TooFarAhead                         => ": that is too far ahead; dates only go up to the year 262142".to_string(),
+        CannotProjectPer(p)                 => format!(": can't project per {}; use days, weeks, fortnights, months, quarters or years", p),
+        LedgerIsUnreadable                  => ": the current ledger couldn't be read, so it may not be empty; use `pfr init --force` to replace it with an empty one".to_string(),
+        UnknownField(t, f)                  => format!(" while attempting to load from the data file: {} has an unknown field `{}` (PFR_STRICT is set)", t, f),
+        NoSuchHistoryEntry(n)               => format!(": there is no history entry {}; see `pfr history` for the numbers", n),
+        NothingToUndo                       => ": there is nothing to undo; see `pfr history` for the changes that have been made".to_string(),
+        WhileReadingStatement(e)            => format!(" while attempting to read the statement: {}", e),
+        InvalidStatement(s)                 => format!(" while attempting to read the statement: {}", s),
+        ChecksFailed(n)                     => format!(": {} check(s) failed", n),
+        OnceNeedsADate                      => ": a one-off transaction needs the date it happens on; give it with --on YYYY-MM-DD".to_string(),
+        WhileAttaching(e)                   => format!(" while attempting to copy the attachment: {}", e),
+        NoAttachments(s)                    => format!(": {} has nothing attached; use `pfr attach`", s),
+        WhileOpening(s, e)                  => format!(" while attempting to open {}: {}", s, e),
+        InvalidShare(p)                     => format!(": a share of {}% doesn't make sense; use a percentage above 0, up to 100", p),
+        AmountIsOutsideRange                => ": the amount should be somewhere from --min to --max".to_string(),
+        PercentOfIncomeOnIncome             => ": an income can't be a percentage of income; give its amount instead".to_string(),
+        TransferNeedsAccounts               => ": a transfer needs the accounts it is between; give them with --from and --to".to_string(),
+        SplitsAreOver100(p)                 => format!(": the splits add up to {}%, which is more than the whole amount", p),
+        AmbiguousName(s, ids)               => format!(": more than one transaction is called {}; use one of their ids instead ({})", s, ids.join(", ")),
+        UnsupportedLedgerVersion(v)         => format!(" while attempting to load from the data file: it was written by a newer version of pfr (format version {})", v),
+        NoPayCycle                          => ": there is no pay cycle to report on; set `pay_cycle` in the config file, e.g. \"pay_cycle\": \"fortnightly\"".to_string(),
+        WhileWritingReport(e)               => format!(" while attempting to write the report: {}", e),
+        WhileReadingTemplate(name, e)       => format!(" while attempting to fill in the template {}: {}", name, e),
+        NetIsBelowThreshold                 => String::new(),
+    };
+
+    match json_output()
+    {
+        true  => println!("{}", json!({ "error": format!("An error occurred{}", message) })),
+        false => println!("An error occurred{}", message),
+    }
+}
+
+
+/// `Ledger`, just an alias for a hashmap of transactions, keyed by their ids.
+type Ledger = HashMap<String, Transaction>;
+
+
+/// the version of the format that ledgers are saved in.
+///
+/// 1: transactions keyed by name, with nothing around them.
+/// 2: `{ "version": 2, "transactions": ... }`, with the transactions keyed by id.
+const LEDGER_VERSION: u64 = 2;
+
+
+/// A ledger as it is saved; the current format, with its version.
+#[derive(Serialize)]
+struct LedgerFile<'a>
+{
+    version: u64,
+    transactions: &'a Ledger,
+}
+
+
+/// the directory given with `--data-dir`, if there was one.
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+
+/// gets the path of the directory that pfr keeps its files in; the one given
+/// with `--data-dir`, or else `$PFR_HOME`, or else `~/.pfr/`.
+fn data_dir() -> Result<PathBuf>
+{
+    if let Some(dir) = DATA_DIR.get()
+    {
+        return Ok(dir.clone());
+    }
+
+    if let Some(dir) = env::var_os("PFR_HOME").filter(|dir| !dir.is_empty())
+    {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let mut home_dir = env::home_dir().ok_or(Error::CouldNotFindHomeDirectory)?;
+    home_dir.push(".pfr/");
+
+    return Ok(home_dir);
+}
+
+
+/// gets path for file called `name`, in the data directory; the one given
+/// with `--data-dir`, or else `$PFR_HOME`, or else `~/.pfr/` (see `data_dir`).
+fn get_path(name: &str) -> Result<PathBuf>
+{
+    let mut path = data_dir()?;
+    path.push(name);
+
+    return Ok(path);
+}
+
+
+/// the names of the ledgers saved with `save`, in order.
+///
+/// Files starting with a `.` are pfr's own (the current ledger, the backup and
+/// so on), and are left out.
+fn saved_ledgers() -> Result<Vec<String>>
+{
+    let entries = fs::read_dir(data_dir()?)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.') && name != "config.json")
+        .collect();
+
+    names.sort();
+
+    return Ok(names);
+}
+
+
+/// Saves the ledger to the pfr data file.
+fn save_ledger(name: &str, ledger: Ledger) -> Result<()>
+{
+    let ledgerfile = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(get_path(name)?)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+    serde_json::to_writer_pretty(ledgerfile, &LedgerFile { version: LEDGER_VERSION, transactions: &ledger })
+        .map_err(Error::DuringSerialisation)
+}
+
+
+/// loads ledger from file
+fn load_ledger(name: &str) -> Result<Ledger>
+{
+    read_ledger(name).map(|(ledger, _)| ledger)
+}
+
+
+/// loads ledger from file, and says whether it was in an older format (and
+/// so has been given ids).
+///
+/// Fields that pfr doesn't know about are ignored, unless `PFR_STRICT=1` is
+/// set, in which case they are an error (handy for catching typos in a
+/// hand-edited ledger).
+fn read_ledger(name: &str) -> Result<(Ledger, bool)>
+{
+    let ledgerfile = OpenOptions::new()
+        .read(true)
+        .open(get_path(name)?)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+    let mut value: serde_json::Value = serde_json::from_reader(ledgerfile)
+        .map_err(Error::DuringDeSerialisation)?;
+
+    let version = value.get("version").map_or(Some(1), |v| v.as_u64()).unwrap_or(0);
+    if version > LEDGER_VERSION
+    {
+        return Err(Error::UnsupportedLedgerVersion(version));
+    }
+
+    if version > 1
+    {
+        value = value["transactions"].take();
+    }
+
+    if strict()
+    {
+        if let Some(transactions) = value.as_object()
+        {
+            let known = transaction_fields();
+            for (key, transaction) in transactions
+            {
+                let mut fields = transaction.as_object().into_iter().flat_map(|t| t.keys());
+                if let Some(field) = fields.find(|f| !known.contains(&f.as_str()))
+                {
+                    return Err(Error::UnknownField(key.clone(), field.clone()));
+                }
+            }
+        }
+    }
+
+    let ledger: Ledger = serde_json::from_value(value)
+        .map_err(Error::DuringDeSerialisation)?;
+
+    if version > 1
+    {
+        return Ok((ledger, false));
+    }
+
+    // version 1 was keyed by name; number the transactions in name order.
+    let mut transactions: Vec<Transaction> = ledger.into_values().collect();
+    transactions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let ledger = transactions.into_iter()
+        .enumerate()
+        .map(|(i, t)| ((i + 1).to_string(), t))
+        .collect();
+
+    return Ok((ledger, true));
+}
+
+
+/// the fields of a `Transaction` as they are stored, taken from its derived
+/// `Deserialize` (the same list `#[serde(deny_unknown_fields)]` checks
+/// against), so a new field is known as soon as it is added.
+fn transaction_fields() -> &'static [&'static str]
+{
+    let mut fields: &'static [&'static str] = &[];
+    let _ = <Transaction as serde::Deserialize>::deserialize(FieldNames(&mut fields));
+    return fields;
+}
+
+
+/// a deserialiser that only notes the field names it is asked for, and then
+/// gives up; see `transaction_fields`.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+
+impl<'de, 'a> serde::Deserializer<'de> for FieldNames<'a>
+{
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    {
+        Err(serde::de::Error::custom("only field names are read"))
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> std::result::Result<V::Value, Self::Error>
+    {
+        *self.0 = fields;
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any!
+    {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+
+/// true if `PFR_STRICT` is set to 1.
+fn strict() -> bool
+{
+    env::var("PFR_STRICT").map(|v| v == "1").unwrap_or(false)
+}
+
+
+/// saves the ledger to the current ledgerfile, and notes the time that it
+/// was changed (and what changed) in the history.
+fn save_current_ledger(ledger: Ledger) -> Result<()>
+{
+    save_current_ledger_noting(ledger, None, Vec::new())
+}
+
+
+/// like `save_current_ledger`, but notes in the history that this undoes
+/// entry number `undoes`, if it does, and which transactions were `restored`
+/// from the trash, so that undoing it can put them back there.
+fn save_current_ledger_noting(ledger: Ledger, undoes: Option<usize>, restored: Vec<Change>) -> Result<()>
+{
+    record_history(&ledger, undoes, restored)?;
+    save_ledger(".current_data", ledger)?;
+
+    fs::write(get_path(".last_modified")?, Local::now().to_rfc3339())
+        .map_err(Error::WhileAttemptingToOpenDataFile)
+}
+
+
+/// the time that the current ledger was last changed, if it is known.
+fn last_modified() -> Option<DateTime<Local>>
+{
+    let timestamp = fs::read_to_string(get_path(".last_modified").ok()?).ok()?;
+
+    DateTime::parse_from_rfc3339(timestamp.trim())
+        .ok()
+        .map(|t| t.with_timezone(&Local))
+}
+
+
+/// prints when the current ledger was last changed.
+fn print_last_modified()
+{
+    match last_modified()
+    {
+        Some(t) => println!("Last updated: {}", format_time(t)),
+        None    => println!("Last updated: unknown"),
+    }
+}
+
+
+/// A change to the current ledger, as kept in `~/.pfr/.history`; one per line.
+#[derive(Serialize, Deserialize)]
+struct HistoryEntry
+{
+    /// when the change was made (RFC3339).
+    time: String,
+
+    /// the pfr command that made it.
+    command: String,
+
+    /// the transactions that were added or changed, as they were afterwards.
+    #[serde(default)]
+    changed: Vec<Change>,
+
+    /// the ids of the transactions that were removed (or their names, in
+    /// entries from before transactions had ids).
+    #[serde(default)]
+    removed: Vec<String>,
+
+    /// for `undo`, the number of the entry that it undid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    undoes: Option<usize>,
+
+    /// for `trash restore`, the transactions taken out of the trash, as they
+    /// were there; undoing it puts them back.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    restored: Vec<Change>,
+}
+
+
+/// A transaction that was added or changed, in a `HistoryEntry`.
+#[derive(Clone, Serialize, Deserialize)]
+struct Change
+{
+    /// its id; left out of entries from before transactions had ids, which
+    /// were keyed by name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+
+    #[serde(flatten)]
+    transaction: Transaction,
+}
+
+
+impl Change
+{
+    /// the key of the transaction in the ledger.
+    fn key(&self) -> &str
+    {
+        self.id.as_ref().unwrap_or(&self.transaction.name)
+    }
+}
+
+
+impl HistoryEntry
+{
+    /// the entry for changing the current ledger from `old` to `new`.
+    fn new(command: String, old: &Ledger, new: &Ledger) -> HistoryEntry
+    {
+        let changed: Vec<Change> = sorted(new).into_iter()
+            .filter(|&(id, t)| old.get(id) != Some(t))
+            .map(|(id, t)| Change { id: Some(id.clone()), transaction: t.clone() })
+            .collect();
+
+        let removed: Vec<String> = sorted(old).into_iter()
+            .filter(|&(id, _)| !new.contains_key(id))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        HistoryEntry { time: Local::now().to_rfc3339(), command, changed, removed, undoes: None, restored: Vec::new() }
+    }
+
+    /// makes the change in `ledger`.
+    fn apply_to(&self, ledger: &mut Ledger)
+    {
+        for change in &self.changed
+        {
+            ledger.insert(change.key().to_string(), change.transaction.clone());
+        }
+
+        for key in &self.removed
+        {
+            ledger.remove(key);
+        }
+    }
+
+    /// true if the entry doesn't change anything.
+    fn is_empty(&self) -> bool
+    {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+
+/// adds an entry to the history for the change from the current ledger to
+/// `ledger`, if anything changed (or if it `undoes` an entry, so that it can't
+/// be undone again).
+///
+/// The first time, the current ledger is recorded in full as well, so that
+/// the history can always be replayed from the start.
+fn record_history(ledger: &Ledger, undoes: Option<usize>, restored: Vec<Change>) -> Result<()>
+{
+    let path = get_path(".history")?;
+    let old = load_current_ledger().unwrap_or_default();
+
+    let mut entries = Vec::new();
+    if !path.exists() && !old.is_empty()
+    {
+        entries.push(HistoryEntry::new("(before history was kept)".to_string(), &Ledger::new(), &old));
+    }
+
+    let command = Some("pfr".to_string()).into_iter().chain(env::args().skip(1)).collect::<Vec<String>>().join(" ");
+    entries.push(HistoryEntry { undoes, restored, ..HistoryEntry::new(command, &old, ledger) });
+    entries.retain(|entry| !entry.is_empty() || entry.undoes.is_some());
+
+    if entries.is_empty()
+    {
+        return Ok(());
+    }
+
+    let mut historyfile = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+    for entry in entries
+    {
+        let line = serde_json::to_string(&entry).map_err(Error::DuringSerialisation)?;
+        writeln!(historyfile, "{}", line).map_err(Error::WhileAttemptingToOpenDataFile)?;
+    }
+
+    Ok(())
+}
+
+
+/// loads every entry in the history, oldest first.
+fn load_history() -> Result<Vec<HistoryEntry>>
+{
+    let path = get_path(".history")?;
+
+    if !path.exists()
+    {
+        return Ok(Vec::new());
+    }
+
+    fs::read_to_string(path)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::DuringDeSerialisation))
+        .collect()
+}
+
+
+/// rebuilds the ledger as it was after the last of `entries`.
+fn replay(entries: &[HistoryEntry]) -> Ledger
+{
+    let mut ledger = Ledger::new();
+
+    for entry in entries
+    {
+        entry.apply_to(&mut ledger);
+    }
+
+    return ledger;
+}
+
+
+/// loads the current ledger
+///
+/// A ledger in the old format, keyed by name, is saved again in the new one
+/// straight away, with a note in the history that the transactions were
+/// given ids, so that the history still replays.
+fn load_current_ledger() -> Result<Ledger>
+{
+    let (ledger, migrated) = read_ledger(".current_data")?;
+
+    if migrated
+    {
+        let path = get_path(".history")?;
+        if path.exists()
+        {
+            let by_name: Ledger = ledger.values().map(|t| (t.name.clone(), t.clone())).collect();
+            let entry = HistoryEntry::new("(gave each transaction an id)".to_string(), &by_name, &ledger);
+            let line = serde_json::to_string(&entry).map_err(Error::DuringSerialisation)?;
+
+            let mut historyfile = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+            writeln!(historyfile, "{}", line).map_err(Error::WhileAttemptingToOpenDataFile)?;
+        }
+
+        save_ledger(".current_data", ledger.clone())?;
+    }
+
+    Ok(ledger)
+}
+
+
+/// the entries of `ledger`, with their ids, sorted by name (and then by id,
+/// for those with the same name).
+fn sorted(ledger: &Ledger) -> Vec<(&String, &Transaction)>
+{
+    let mut entries: Vec<(&String, &Transaction)> = ledger.iter().collect();
+    entries.sort_by(|a, b| a.1.name.cmp(&b.1.name).then(a.0.len().cmp(&b.0.len())).then(a.0.cmp(b.0)));
+
+    return entries;
+}
+
+
+/// finds the id of the entry in `ledger` picked out by `key`, which is either
+/// its id or its name.
+///
+/// Errors if there is no such entry, or if more than one has that name.
+fn find(ledger: &Ledger, key: &str) -> Result<String>
+{
+    if ledger.contains_key(key)
+    {
+        return Ok(key.to_string());
+    }
+
+    let mut ids: Vec<String> = sorted(ledger).into_iter()
+        .filter(|&(_, t)| t.name == key)
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    match ids.len()
+    {
+        0 => Err(Error::NoSuchTransaction(key.to_string())),
+        1 => Ok(ids.remove(0)),
+        _ => Err(Error::AmbiguousName(key.to_string(), ids)),
+    }
+}
+
+
+/// Optional settings, read from `~/.pfr/config.json`.
+/// Anything left out falls back to the defaults.
+#[derive(Default, Deserialize)]
+struct Config
+{
+    /// days in a month; defaults to 365/12, about 30.42.
+    days_per_month: Option<f64>,
+
+    /// weeks in a month; defaults to 365/7/12, about 4.35.
+    weeks_per_month: Option<f64>,
+
+    /// working days in a month; defaults to 5 per week.
+    workdays_per_month: Option<f64>,
+
+    /// the names of the buckets used by `allocate`; defaults to needs, wants and savings.
+    buckets: Option<Vec<String>>,
+
+    /// which bucket the expenses in each category belong to.
+    #[serde(default)]
+    category_buckets: HashMap<String, String>,
+
+    /// the net that `report` aims for, unless `--savings-goal` is given.
+    savings_goal: Option<f64>,
+
+    /// the effective tax rate (%) taken off incomes added with `--gross`; defaults to 0.
+    tax_rate: Option<f64>,
+
+    /// income tax brackets, for working out the tax on `--gross` incomes in
+    /// place of `tax_rate`; each rate (%) applies to the yearly income above
+    /// `from`, up to the next bracket.
+    #[serde(default)]
+    tax_brackets: Vec<TaxBracket>,
+
+    /// how to change the case of new categories and accounts; left as typed by default.
+    normalize_case: Option<LabelCase>,
+
+    /// how often you are paid, for `report --per payday`, e.g. fortnightly.
+    pay_cycle: Option<Frequency>,
+
+    /// a day you were paid on, which the pay periods are counted from.
+    payday: Option<NaiveDate>,
+
+    /// what is in each account, for `networth`.
+    #[serde(default)]
+    balances: HashMap<String, f64>,
+
+    /// what is owed on each debt, such as a loan or credit card, for `networth`.
+    #[serde(default)]
+    debts: HashMap<String, f64>,
+
+    /// how `report` suggests sharing out a surplus, in order.
+    #[serde(default)]
+    surplus_split: Vec<SurplusShare>,
+}
+
+
+/// Where some of a surplus should go, as a percentage of it.
+#[derive(Deserialize)]
+struct SurplusShare
+{
+    to: String,
+    percent: f64,
+}
+
+
+/// One of the income tax brackets in the config.
+#[derive(Deserialize)]
+struct TaxBracket
+{
+    from: f64,
+    rate: f64,
+}
+
+
+impl Config
+{
+    /// with tax brackets, sets `tax_rate` to the effective rate of tax on the
+    /// yearly gross income in `ledger`, as of `date`.
+    fn apply_tax_brackets(&mut self, ledger: &Ledger, date: NaiveDate)
+    {
+        if self.tax_brackets.is_empty()
+        {
+            return;
+        }
+
+        let gross = ledger.values()
+            .filter(|t| t.add_type == AddType::income && t.gross && t.active_in(date))
+            .filter_map(|t| t.amount.money().map(|money| money.times(Ratio::whole(12) * t.active_fraction() / t.months_factor(self))))
+            .fold(Money::default(), |total, money| total + money);
+
+        let income = gross.mills as f64 / 1000.0;
+        self.tax_brackets.sort_by(|a, b| a.from.total_cmp(&b.from));
+
+        let tax: f64 = self.tax_brackets.iter().enumerate()
+            .map(|(i, bracket)|
+            {
+                let to = self.tax_brackets.get(i + 1).map_or(f64::INFINITY, |next| next.from);
+                (income.min(to) - bracket.from).max(0.0) * bracket.rate / 100.0
+            })
+            .sum();
+
+        self.tax_rate = Some(if income > 0.0 { tax / income * 100.0 } else { 0.0 });
+    }
+
+    /// the first and last days of the pay period that `today` is in; only
+    /// for weekly and fortnightly pay cycles, and only if `payday` is set.
+    fn pay_period(&self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)>
+    {
+        let days = match self.pay_cycle?
+        {
+            Frequency::weekly      => 7,
+            Frequency::fortnightly => 14,
+            _                      => return None,
+        };
+
+        let start = today - chrono::Duration::days((today - self.payday?).num_days().rem_euclid(days));
+        return Some((start, start + chrono::Duration::days(days - 1)));
+    }
+}
+
+
+/// A way of writing categories and accounts consistently.
+#[derive(Clone, Copy, Deserialize)]
+enum LabelCase
+{
+    /// e.g. "eating out".
+    lower,
+
+    /// e.g. "Eating Out".
+    title,
+}
+
+
+/// tidies up a category or account name before it is stored; trims it, and
+/// changes its case if the config says to.
+fn normalise_label(label: &str, config: &Config) -> String
+{
+    let label = label.trim();
+
+    match config.normalize_case
+    {
+        None                   => label.to_string(),
+        Some(LabelCase::lower) => label.to_lowercase(),
+        Some(LabelCase::title) => label.split(' ')
+            .map(|word|
+            {
+                let mut chars = word.chars();
+                match chars.next()
+                {
+                    Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+                    None        => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+    }
+}
+
+
+/// true if `label` is `filter`, ignoring case and any spaces around them; how
+/// the filters on categories and accounts compare.
+fn same_label(label: &str, filter: &str) -> bool
+{
+    label.trim().eq_ignore_ascii_case(filter.trim())
+}
+
+
+/// loads the config file, if there is one.
+fn load_config() -> Result<Config>
+{
+    let path = get_path("config.json")?;
+
+    if !path.exists()
+    {
+        return Ok(Config::default());
+    }
+
+    let configfile = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(Error::WhileAttemptingToOpenDataFile)?;
+
+    serde_json::from_reader(configfile)
+        .map_err(Error::WhileReadingConfig)
+}
+
+
+/// creates the data directory if needed, and clears the current ledger.
+/// Refuses to clear a ledger with entries in it, unless `force` is set.
+fn init(force: bool) -> Result<()>
+{
+    let data_dir = data_dir()?;
+
+    if !data_dir.exists()
+    {
+        fs::create_dir_all(data_dir)
+            .map_err(Error::DuringInitialisation)?;
+    }
+
+    if !force && get_path(".current_data")?.exists()
+    {
+        // one that can't be read might have anything in it.
+        match load_current_ledger()
+        {
+            Ok(ledger) if ledger.is_empty() => (),
+            Ok(ledger)                      => return Err(Error::LedgerIsNotEmpty(ledger.len())),
+            Err(_)                          => return Err(Error::LedgerIsUnreadable),
+        }
+    }
+
+    // the new ledger isn't any of the saved ones; it's fine if there was no name to forget.
+    let _ = fs::remove_file(get_path(".current_name")?);
+
+    save_current_ledger(Ledger::new())
+}
+
+
+/// loads the trash; a ledger of removed entries.
+fn load_trash() -> Result<Ledger>
+{
+    match get_path(".trash")?.exists()
+    {
+        true  => load_ledger(".trash"),
+        false => Ok(Ledger::new()),
+    }
+}
+
+
+/// Adds `transaction` to `ledger` under a new id, one more than the biggest
+/// in it, and returns the id.
+fn insert(ledger: &mut Ledger, transaction: Transaction) -> String
+{
+    let id = ledger.keys()
+        .filter_map(|id| id.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0) + 1;
+
+    ledger.insert(id.to_string(), transaction);
+
+    return id.to_string();
+}
+
+
+/// splits a transaction name into lowercase words, e.g. "Shell Kaitaia" -> ["shell", "kaitaia"].
+fn tokens(name: &str) -> Vec<String>
+{
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+
+/// the form of a name used to compare it with others; trimmed and lowercase,
+/// with runs of whitespace collapsed, e.g. " Gym  Membership" -> "gym membership".
+fn normalise_name(name: &str) -> String
+{
+    name.split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+
+/// Suggests a category for `transaction`, based on the transactions of the
+/// same type in `ledger` that share a word with its name.
+///
+/// The most common category among those is suggested, as long as no other
+/// category is just as common.
+fn suggest_category(ledger: &Ledger, transaction: &Transaction) -> Option<String>
+{
+    let words = tokens(&transaction.name);
+    let mut votes: HashMap<&str, usize> = HashMap::new();
+
+    for other in ledger.values().filter(|t| t.add_type == transaction.add_type)
+    {
+        if let Some(ref category) = other.category
+        {
+            if tokens(&other.name).iter().any(|t| words.contains(t))
+            {
+                *votes.entry(category).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let best = votes.values().cloned().max()?;
+    let mut winners = votes.iter().filter(|&(_, &count)| count == best);
+
+    match (winners.next(), winners.next())
+    {
+        (Some((category, _)), None) => Some(category.to_string()),
+        _                           => None,
+    }
+}
+
+
+/// Asks the user a yes/no question, defaulting to yes.
+fn confirm(question: &str) -> bool
+{
+    print!("{} [Y/n] ", question);
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err()
+    {
+        return false;
+    }
+
+    let answer = answer.trim().to_lowercase();
+    return answer.is_empty() || answer == "y" || answer == "yes";
+}
+
+
+/// Adds a new entry to the ledger.
+///
+/// If no category was given, and one can be guessed from similarly named
+/// transactions, the user is asked whether to use it (only when run from a
+/// terminal).
+fn add(mut ac: Transaction) -> Result<()>
+{
+    if ac.freq == Frequency::once && ac.start_date.is_none()
+    {
+        return Err(Error::OnceNeedsADate);
+    }
+
+    if ac.add_type == AddType::income && ac.amount.money().is_none()
+    {
+        return Err(Error::PercentOfIncomeOnIncome);
+    }
+
+    if !ac.range_contains_amount()
+    {
+        return Err(Error::AmountIsOutsideRange);
+    }
+
+    if let Some(share) = ac.share.filter(|&p| p <= 0.0 || p > 100.0)
+    {
+        return Err(Error::InvalidShare(share));
+    }
+
+    if ac.add_type == AddType::transfer && (ac.account.is_none() || ac.to_account.is_none())
+    {
+        return Err(Error::TransferNeedsAccounts);
+    }
+
+    let split = ac.splits.iter().map(|s| s.percent).sum::<f64>();
+    if split > 100.0
+    {
+        return Err(Error::SplitsAreOver100(split));
+    }
+
+    let mut ledger = load_current_ledger()?;
+    let config = load_config()?;
+
+    ac.category = ac.category.map(|c| normalise_label(&c, &config));
+    ac.splits.iter_mut().for_each(|s| s.category = normalise_label(&s.category, &config));
+    ac.account = ac.account.map(|a| normalise_label(&a, &config));
+    ac.to_account = ac.to_account.map(|a| normalise_label(&a, &config));
+    ac.payee = ac.payee.map(|p| normalise_label(&p, &config));
+    ac.tags = ac.tags.iter().map(|t| normalise_label(t, &config)).collect();
+
+    if ac.category.is_none() && ac.add_type != AddType::transfer && std::io::stdin().is_terminal()
+    {
+        if let Some(category) = suggest_category(&ledger, &ac)
+        {
+            if confirm(&format!("Put {} in the category {}?", ac.name, category))
+            {
+                ac.category = Some(category);
+            }
+        }
+    }
+
+    if ledger.values().any(|t| t.name == ac.name)
+    {
+        println!("note: there is already a transaction called {}; use their ids to tell them apart", ac.name);
+    }
+
+    let id = insert(&mut ledger, ac);
+    if let Some(transaction) = ledger.get_mut(&id)
+    {
+        let attachments = std::mem::take(&mut transaction.attachments);
+        for path in attachments
+        {
+            transaction.attachments.push(store_attachment(&id, &path)?);
+        }
+    }
+
+    save_current_ledger(ledger)
+}
+
+
+/// Asks `question` until the answer passes `check`, giving the answer; or
+/// `None` at the end of the input.
+fn ask<F>(question: &str, check: F) -> Option<String>
+    where F: Fn(&str) -> std::result::Result<(), String>
+{
+    loop
+    {
+        print!("{} ", question);
+        let _ = std::io::stdout().flush();
+
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer)
+        {
+            Ok(0) | Err(_) => return None,
+            Ok(_)          => (),
+        }
+
+        let answer = answer.trim().to_string();
+        match check(&answer)
+        {
+            Ok(())   => return Some(answer),
+            Err(why) => println!("{}", why),
+        }
+    }
+}
+
+
+/// `answer`, or the one of `known` that it is the start of, if there is only
+/// one; ignoring case.
+fn complete(answer: &str, known: &[String]) -> String
+{
+    let answer = answer.to_lowercase();
+    if let Some(exact) = known.iter().find(|k| k.to_lowercase() == answer)
+    {
+        return exact.clone();
+    }
+
+    let mut starting = known.iter().filter(|k| k.to_lowercase().starts_with(&answer));
+    match (starting.next(), starting.next())
+    {
+        (Some(only), None) if !answer.is_empty() => only.clone(),
+        _                                        => answer,
+    }
+}
+
+
+/// Adds a new entry, asking for each part of it in turn; categories and
+/// accounts that are already in use can be picked by typing the start of one.
+///
+/// The answers are then read as if they had been given to `add`.
+fn add_wizard() -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+
+    let labels = |label: &dyn Fn(&Transaction) -> Vec<String>| -> Vec<String>
+    {
+        let mut labels: Vec<String> = ledger.values().flat_map(label).collect();
+        labels.sort();
+        labels.dedup();
+        return labels;
+    };
+
+    let categories = labels(&|t| t.category.iter().cloned().collect());
+    let accounts = labels(&|t| t.account.iter().chain(&t.to_account).cloned().collect());
+
+    let optional = |_: &str| Ok(());
+    let required = |answer: &str| match answer.is_empty()
+    {
+        true  => Err("this one is needed".to_string()),
+        false => Ok(()),
+    };
+
+    let choices = |known: &[String]| match known.is_empty()
+    {
+        true  => String::new(),
+        false => format!(" [{}]", known.join(", ")),
+    };
+
+    // everything that's asked for, as `add` would be given it.
+    let mut args = vec!["add".to_string()];
+    let mut wizard = || -> Option<()>
+    {
+        let add_type = ask("Type (income, expense or transfer):", |a| AddType::from_str(a).map(|_| ()).map_err(|_| "that's not one of those".to_string()))?;
+        let add_type = AddType::from_str(&add_type).ok()?;
+        args.push(add_type.to_string());
+
+        let freq = ask(&format!("How often ({}):", Frequency::variants().join(", ")), |a| Frequency::from_str(a).map(|_| ()).map_err(|_| "that's not one of those".to_string()))?;
+        args.push(freq.to_lowercase());
+
+        if freq.eq_ignore_ascii_case("once")
+        {
+            args.push("--on".to_string());
+            args.push(ask("On (YYYY-MM-DD):", |a| parse_date(a).map(|_| ()))?);
+        }
+
+        args.push(ask("Name:", required)?);
+
+        args.push(ask("Amount:", |a| match (Amount::from_str(a), add_type)
+        {
+            (Err(_), _)                                               => Err(format!("{} isn't an amount; e.g. 12.50, or 15% of the income", a)),
+            (Ok(amount), AddType::income) if amount.money().is_none() => Err("an income has to be an amount of money, not a percentage".to_string()),
+            (Ok(_), _)                                                => Ok(()),
+        })?);
+
+        if add_type != AddType::transfer
+        {
+            let category = ask(&format!("Category{} (blank for none):", choices(&categories)), optional)?;
+            if !category.is_empty()
+            {
+                args.push("--category".to_string());
+                args.push(complete(&category, &categories));
+            }
+        }
+
+        let account = match add_type
+        {
+            AddType::transfer => ask(&format!("From account{}:", choices(&accounts)), required)?,
+            _                 => ask(&format!("Account{} (blank for none):", choices(&accounts)), optional)?,
+        };
+        if !account.is_empty()
+        {
+            args.push("--account".to_string());
+            args.push(complete(&account, &accounts));
+        }
+
+        if add_type == AddType::transfer
+        {
+            args.push("--to".to_string());
+            args.push(complete(&ask(&format!("To account{}:", choices(&accounts)), required)?, &accounts));
+        }
+
+        Some(())
+    };
+
+    if wizard().is_none()
+    {
+        println!();
+        return Ok(());
+    }
+
+    let matches = Transaction::clap().get_matches_from_safe(args).unwrap_or_else(|e| e.exit());
+    add(Transaction::from_clap(&matches))
+}
+
+
+/// Prints every field of the entry `name` (an id or a name), one per line.
+fn show(name: String) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let id = find(&ledger, &name)?;
+    let t = &ledger[&id];
+
+    let months: Vec<String> = t.skip_months.iter().map(|m| m.to_string()).collect();
+    let splits: Vec<String> = t.splits.iter().map(|s| format!("{} {}%", s.category, s.percent)).collect();
+    let range = match (t.min, t.max)
+    {
+        (None, None) => None,
+        (min, max)   => Some(format!("{} to {}", min.map_or("?".to_string(), Money::plain), max.map_or("?".to_string(), Money::plain))),
+    };
+
+    let fields = [
+        ("id",         Some(id.clone())),
+        ("name",       Some(t.name.clone())),
+        ("type",       Some(t.add_type.to_string())),
+        ("frequency",  Some(t.describe_freq())),
+        ("amount",     Some(t.amount.to_string().trim().to_string())),
+        ("category",   t.category.clone()),
+        ("splits",     Some(splits.join(", ")).filter(|s| !s.is_empty())),
+        ("account",    t.account.clone()),
+        ("to account", t.to_account.clone()),
+        ("payee",      t.payee.clone()),
+        ("tags",       Some(t.tags.join(", ")).filter(|s| !s.is_empty())),
+        ("starts",     t.start_date.map(format_date)),
+        ("ends",       t.end_date.map(format_date)),
+        ("due day",    t.due_day.map(|d| d.to_string())),
+        ("skips",      Some(months.join(", ")).filter(|s| !s.is_empty())),
+        ("share",      t.share.map(|p| format!("{}% yours", p))),
+        ("flexible",   Some(t.discretionary).filter(|&d| d).map(|_| "yes".to_string())),
+        ("gross",      Some(t.gross).filter(|&g| g).map(|_| "yes".to_string())),
+        ("estimated",  Some(t.estimated).filter(|&e| e).map(|_| "yes".to_string())),
+        ("range",      range),
+        ("note",       t.note.clone()),
+        ("attached",   Some(t.attachments.join(", ")).filter(|s| !s.is_empty())),
+        ("paused",     Some(t.paused).filter(|&p| p).map(|_| "yes".to_string())),
+    ];
+
+    for (label, value) in fields.iter()
+    {
+        if let Some(ref value) = *value
+        {
+            println!("{:<12}{}", format!("{}:", label), value);
+        }
+    }
+
+    Ok(())
+}
+
+
+/// true if `path` is a URL rather than a file, e.g. `https://example.com/lease`.
+fn is_url(path: &str) -> bool
+{
+    path.contains("://")
+}
+
+
+/// Copies the file at `path` into the attachments directory, as an attachment
+/// of the entry `id`, and returns where it is kept, relative to the data
+/// directory. URLs are kept as they are.
+fn store_attachment(id: &str, path: &str) -> Result<String>
+{
+    if is_url(path)
+    {
+        return Ok(path.to_string());
+    }
+
+    let source = PathBuf::from(path);
+    let file_name = source.file_name()
+        .ok_or_else(|| Error::WhileAttaching(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a file", path))))?
+        .to_string_lossy()
+        .into_owned();
+
+    let dir = get_path("attachments")?;
+    fs::create_dir_all(&dir).map_err(Error::WhileAttaching)?;
+
+    let stored = format!("attachments/{}-{}", id, file_name);
+    fs::copy(&source, get_path(&stored)?).map_err(Error::WhileAttaching)?;
+
+    return Ok(stored);
+}
+
+
+/// Attaches the file or URL `path` to the entry `name`, an id or a name.
+fn attach(name: String, path: String) -> Result<()>
+{
+    let mut ledger = load_current_ledger()?;
+    let id = find(&ledger, &name)?;
+    let stored = store_attachment(&id, &path)?;
+
+    if let Some(transaction) = ledger.get_mut(&id)
+    {
+        println!("attached {} to {}", stored, transaction.name);
+        transaction.attachments.push(stored);
+    }
+
+    save_current_ledger(ledger)
+}
+
+
+/// Opens each of the attachments of the entry `name` (an id or a name) with
+/// the system's usual program for it.
+fn open(name: String) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let id = find(&ledger, &name)?;
+    let transaction = &ledger[&id];
+
+    if transaction.attachments.is_empty()
+    {
+        return Err(Error::NoAttachments(transaction.name.clone()));
+    }
+
+    for attachment in &transaction.attachments
+    {
+        let target = match is_url(attachment)
+        {
+            true  => attachment.clone(),
+            false => get_path(attachment)?.to_string_lossy().into_owned(),
+        };
+
+        let mut command = match cfg!(target_os = "windows")
+        {
+            true  =>
+            {
+                let mut command = std::process::Command::new("cmd");
+                command.args(["/C", "start", ""]);
+                command
+            },
+            false => std::process::Command::new(if cfg!(target_os = "macos") { "open" } else { "xdg-open" }),
+        };
+
+        command.arg(&target)
+            .status()
+            .map_err(|e| Error::WhileOpening(target.clone(), e))?;
+    }
+
+    Ok(())
+}
+
+
+/// Pauses (or resumes) the entry `name`, an id or a name.
+///
+/// A paused entry stays in the ledger (and its history), but isn't active in
+/// any month, so it is left out of reports, projections and so on.
+fn set_paused(name: String, paused: bool) -> Result<()>
+{
+    let mut ledger = load_current_ledger()?;
+    let id = find(&ledger, &name)?;
+    let transaction = ledger.get_mut(&id).ok_or(Error::NoSuchTransaction(name.clone()))?;
+
+    if transaction.paused == paused
+    {
+        match paused
+        {
+            true  => println!("{} is already paused", transaction.name),
+            false => println!("{} isn't paused", transaction.name),
+        }
+        return Ok(());
+    }
+
+    transaction.paused = paused;
+    match paused
+    {
+        true  => println!("paused {}", transaction.name),
+        false => println!("resumed {}", transaction.name),
+    }
+
+    save_current_ledger(ledger)
+}
+
+
+/// Changes the fields given in `ec` of an existing entry, leaving the rest as
+/// they were.
+fn edit(ec: EditCommand) -> Result<()>
+{
+    let mut ledger = load_current_ledger()?;
+    let config = load_config()?;
+
+    let id = find(&ledger, &ec.name)?;
+    let transaction = ledger.get_mut(&id).ok_or(Error::NoSuchTransaction(ec.name.clone()))?;
+
+    if let Some(amount) = ec.amount
+    {
+        transaction.amount = amount;
+    }
+
+    if let Some(min) = ec.min
+    {
+        transaction.min = Some(min);
+    }
+
+    if let Some(max) = ec.max
+    {
+        transaction.max = Some(max);
+    }
+
+    if let Some(freq) = ec.freq
+    {
+        transaction.freq = freq;
+    }
+
+    if let Some(every) = ec.every
+    {
+        transaction.every = Some(every).filter(|&n| n > 1);
+    }
+
+    if let Some(category) = ec.category
+    {
+        transaction.category = Some(normalise_label(&category, &config));
+    }
+
+    if let Some(account) = ec.account
+    {
+        transaction.account = Some(normalise_label(&account, &config));
+    }
+
+    if let Some(payee) = ec.payee
+    {
+        transaction.payee = Some(normalise_label(&payee, &config));
+    }
+
+    if let Some(note) = ec.note
+    {
+        transaction.note = Some(note);
+    }
+
+    if transaction.freq == Frequency::once && transaction.start_date.is_none()
+    {
+        return Err(Error::OnceNeedsADate);
+    }
+
+    if transaction.add_type == AddType::income && transaction.amount.money().is_none()
+    {
+        return Err(Error::PercentOfIncomeOnIncome);
+    }
+
+    if !transaction.range_contains_amount()
+    {
+        return Err(Error::AmountIsOutsideRange);
+    }
+
+    println!("updated {}", ec.name);
+    save_current_ledger(ledger)
+}
+
+
+/// Removes entries from the ledger, either by id or name, or all those
+/// matching the given filters. Names that aren't in the ledger are ignored.
+///
+/// Removed entries are moved to the trash, keeping their ids.
+fn rm(rc: RmCommand) -> Result<()>
+{
+    if rc.names.is_empty() && !rc.has_filter()
+    {
+        return Err(Error::NothingToRemove);
+    }
+
+    let mut ledger = load_current_ledger()?;
+
+    // a pattern removes every transaction whose name it matches, ignoring case.
+    let mut named = Vec::new();
+    for name in &rc.names
+    {
+        let found = match name.contains(['*', '?'])
+        {
+            true  => ledger.iter()
+                .filter(|(_, t)| glob_matches(&name.to_lowercase(), &t.name.to_lowercase()))
+                .map(|(id, _)| id.clone())
+                .collect(),
+
+            false => match find(&ledger, name)
+            {
+                Ok(id)                           => vec![id],
+                Err(Error::NoSuchTransaction(_)) => Vec::new(),
+                Err(e)                           => return Err(e),
+            },
+        };
+
+        if found.is_empty()
+        {
+            println!("note: nothing is called {}", name);
+        }
+
+        named.extend(found);
+    }
+
+    let removed: Vec<String> = sorted(&ledger).into_iter()
+        .filter(|&(id, t)| named.contains(id) || rc.matches(t))
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    if rc.dry_run
+    {
+        removed.iter().for_each(|id| println!("would remove {}", ledger[id].name));
+        println!("{} in all", removed.len());
+        return Ok(());
+    }
+
+    let mut trash = load_trash()?;
+
+    for id in &removed
+    {
+        if let Some(transaction) = ledger.remove(id)
+        {
+            println!("removed {}", transaction.name);
+            trash.insert(id.clone(), transaction);
+        }
+    }
+
+    if removed.len() > 1
+    {
+        println!("{} removed; `pfr trash restore <id or name>` puts one back, and `pfr undo` puts them all back", removed.len());
+    }
+
+    save_ledger(".trash", trash)?;
+    save_current_ledger(ledger)
+}
+
+
+/// Changes the category of every transaction in category `from` to `to`, or
+/// removes it from them if `to` is `None`. Splits into `from` are moved too,
+/// or dropped (putting their share back in the transaction's own category).
+fn recategorize(from: String, to: Option<String>) -> Result<()>
+{
+    let mut ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let to = to.map(|to| normalise_label(&to, &config));
+
+    let mut changed = 0;
+    for transaction in ledger.values_mut().filter(|t| t.category.as_ref().is_some_and(|c| same_label(c, &from)) || t.splits.iter().any(|s| same_label(&s.category, &from)))
+    {
+        if transaction.category.as_ref().is_some_and(|c| same_label(c, &from))
+        {
+            transaction.category = to.clone();
+        }
+
+        match to
+        {
+            Some(ref to) => transaction.splits.iter_mut().filter(|s| same_label(&s.category, &from)).for_each(|s| s.category = to.clone()),
+            None         => transaction.splits.retain(|s| !same_label(&s.category, &from)),
+        }
+
+        changed += 1;
+    }
+
+    match to
+    {
+        Some(ref to) => println!("moved {} transaction(s) from {} to {}", changed, from, to),
+        None         => println!("cleared the category of {} transaction(s)", changed),
+    }
+
+    save_current_ledger(ledger)
+}
+
+
+/// Manages the trash.
+fn trash(tc: TrashCommand) -> Result<()>
+{
+    match tc
+    {
+        TrashCommand::list =>
+        {
+            let trash = load_trash()?;
+            let painter = Painter::new(ColorChoice::auto);
+            let width = name_width(trash.values());
+            sorted(&trash).into_iter().for_each(|(id, t)| print_transaction(&painter, id, t, width));
+            Ok(())
+        },
+
+        TrashCommand::restore { name } =>
+        {
+            let mut trash = load_trash()?;
+            let mut ledger = load_current_ledger()?;
+
+            let id = find(&trash, &name)?;
+            let transaction = trash.remove(&id).ok_or(Error::NoSuchTransaction(name))?;
+            let restored = vec![Change { id: Some(id.clone()), transaction: transaction.clone() }];
+
+            match ledger.contains_key(&id)
+            {
+                true  =>
+                {
+                    let new_id = insert(&mut ledger, transaction);
+                    println!("restored {} as {}, since {} is taken", id, new_id, id);
+                },
+                false =>
+                {
+                    ledger.insert(id, transaction);
+                },
+            }
+
+            save_current_ledger_noting(ledger, None, restored)?;
+            save_ledger(".trash", trash)
+        },
+
+        TrashCommand::empty => save_ledger(".trash", Ledger::new()),
+    }
+}
+
+
+/// Lists the entries in the ledger, with their ids, by name.
+///
+/// With `--limit` or `--offset`, only that window of the entries is shown,
+/// followed by a line saying which ones they were.
+/// `items` in sections by `group_by`, in order of name, then those with
+/// nothing to group by as "(unassigned)". Names that only differ in case go
+/// together, under whichever spelling sorts first, and an item with more than
+/// one name (tags) is in each of their sections.
+fn groups<'a, T, I, F>(items: I, group_by: GroupBy, transaction: F) -> Vec<(String, Vec<T>)>
+    where T: Copy, I: Iterator<Item = T>, F: Fn(T) -> &'a Transaction
+{
+    let mut groups: BTreeMap<String, (String, Vec<T>)> = BTreeMap::new();
+    let mut unassigned = Vec::new();
+
+    for item in items
+    {
+        let mut keys = group_by.keys(transaction(item));
+        keys.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b)));
+        keys.dedup_by(|a, b| same_label(a, b));
+
+        if keys.is_empty()
+        {
+            unassigned.push(item);
+        }
+
+        for key in keys
+        {
+            let group = groups.entry(key.to_lowercase()).or_insert_with(|| (key.clone(), Vec::new()));
+            if key < group.0
+            {
+                group.0 = key;
+            }
+            group.1.push(item);
+        }
+    }
+
+    return groups.into_values()
+        .chain(Some(("(unassigned)".to_string(), unassigned)))
+        .filter(|(_, items)| !items.is_empty())
+        .collect();
+}
+
+
+fn list(lc: ListCommand) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let painter = Painter::new(lc.color);
+
+    if lc.format == ListFormat::text && !json_output()
+    {
+        print_last_modified();
+    }
+
+    // the filters all have to match.
+    let today = Local::now().date_naive();
+    let matches = |filter: &Option<String>, label: &Option<String>| filter.as_ref()
+        .is_none_or(|filter| label.as_ref().is_some_and(|label| same_label(label, filter)));
+
+    let mut transactions: Vec<(&String, &Transaction)> = sorted(&ledger).into_iter()
+        .filter(|&(_, t)| !lc.active || t.active_in(today))
+        .filter(|&(_, t)| lc.tag.as_ref().is_none_or(|tag| t.tags.iter().any(|t| same_label(t, tag))))
+        .filter(|&(_, t)| lc.add_type.is_none_or(|add_type| t.add_type == add_type))
+        .filter(|&(_, t)| matches(&lc.category, &t.category))
+        .filter(|&(_, t)| matches(&lc.account, &t.account) || (lc.account.is_some() && matches(&lc.account, &t.to_account)))
+        .filter(|&(_, t)| lc.freq.is_none_or(|freq| t.freq == freq))
+        .collect();
+
+    // those that don't happen this month have no monthly amount.
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+    let monthly = |t: &Transaction| summary.rows.iter().find(|row| ptr::eq(row.transaction, t)).map(|row| row.amount);
+
+    // stable, so that ties stay in order of name; one-offs come after the
+    // rest by frequency, and entries with no monthly amount after the rest by amount.
+    match lc.sort
+    {
+        None | Some(ListSort::name) => (),
+        Some(ListSort::amount)      => transactions.sort_by_key(|&(_, t)| std::cmp::Reverse(monthly(t).map(|m| m.mills.unsigned_abs()))),
+        Some(ListSort::category)    => transactions.sort_by(|a, b| a.1.category.cmp(&b.1.category)),
+        Some(ListSort::freq)        => transactions.sort_by(|a, b|
+        {
+            let key = |t: &Transaction| (t.freq == Frequency::once, t.months_factor(&config));
+            key(a.1).cmp(&key(b.1))
+        }),
+    }
+
+    if lc.reverse
+    {
+        transactions.reverse();
+    }
+
+    let count = transactions.len();
+    let start = lc.offset.min(count);
+    let end = lc.limit.map_or(count, |limit| start.saturating_add(limit).min(count));
+
+    // as stored in the ledger (see `pfr schema`), with their ids.
+    if json_output()
+    {
+        let entries: Vec<Change> = transactions[start..end].iter()
+            .map(|&(id, t)| Change { id: Some(id.clone()), transaction: t.clone() })
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&entries).map_err(Error::DuringSerialisation)?);
+        return Ok(());
+    }
+
+    if lc.format == ListFormat::csv
+    {
+        print_rows(ExportFormat::csv, "monthly", transactions[start..end].iter().map(|&(_, t)| (t, monthly(t))));
+        return Ok(());
+    }
+
+    let width = name_width(transactions[start..end].iter().map(|&(_, t)| t));
+    match lc.group_by
+    {
+        None => transactions[start..end].iter().for_each(|&(id, t)| print_transaction(&painter, id, t, width)),
+
+        Some(group_by) =>
+        {
+            for (name, entries) in groups(transactions[start..end].iter().cloned(), group_by, |(_, t)| t)
+            {
+                println!("{}:", name);
+                entries.iter().for_each(|&(id, t)| print_transaction(&painter, id, t, width));
+                println!();
+            }
+        }
+    }
+
+    if lc.limit.is_some() || lc.offset > 0
+    {
+        match start < end
+        {
+            true  => println!("showing {}–{} of {}", start + 1, end, count),
+            false => println!("showing 0 of {}", count),
+        }
+    }
+
+    Ok(())
+}
+
+
+/// the width of a column holding every one of `names`, with a gap after it;
+/// at least `min`. On a terminal, it is no more than `max`, and longer names
+/// are cut short with `fit`.
+fn column_width<'a, I>(names: I, min: usize, max: usize) -> usize
+    where I: Iterator<Item = &'a str>
+{
+    let widest = names.map(|name| name.chars().count() + 2).max().unwrap_or(0).max(min);
+
+    match std::io::stdout().is_terminal()
+    {
+        true  => widest.min(max.max(min)),
+        false => widest,
+    }
+}
+
+
+/// `text`, cut short with an ellipsis if it is over `width` characters long.
+fn fit(text: &str, width: usize) -> String
+{
+    match text.chars().count() > width
+    {
+        true  => text.chars().take(width.saturating_sub(1)).chain(Some('…')).collect(),
+        false => text.to_string(),
+    }
+}
+
+
+/// the width of the name column in `list` for `transactions`; the rest of the
+/// line takes up about 40 characters.
+fn name_width<'a, I>(transactions: I) -> usize
+    where I: Iterator<Item = &'a Transaction>
+{
+    column_width(transactions.map(|t| t.name.as_str()), 20, terminal_width().saturating_sub(40))
+}
+
+
+/// Prints a line for `transaction`, with its `id`, as it appears in `list`,
+/// with its name in a column `width` wide (see `name_width`).
+fn print_transaction(painter: &Painter, id: &str, transaction: &Transaction, width: usize)
+{
+    // a percentage is coloured like the money it stands for.
+    let amount = transaction.amount.money().unwrap_or(Money { mills: 1 });
+    let sign = match transaction.add_type
+    {
+        AddType::income   => amount,
+        AddType::expense  => -amount,
+        AddType::transfer => Money::default(),
+    };
+
+    let amount = painter.paint(&transaction.amount.to_string(), sign);
+    let extra: Vec<&str> = Some("(paused)").filter(|_| transaction.paused).into_iter()
+        .chain(transaction.note.as_deref())
+        .collect();
+
+    match extra.is_empty()
+    {
+        false => println!("{: >4}\t{}\t{: <14?}\t{: <w$}\t{}\t{}", id, transaction.describe_freq(), transaction.add_type, fit(&transaction.name, width - 1), amount, extra.join(" "), w = width),
+        true  => println!("{: >4}\t{}\t{: <14?}\t{: <w$}\t{}", id, transaction.describe_freq(), transaction.add_type, fit(&transaction.name, width - 1), amount, w = width),
+    }
+}
+
+
+impl Frequency
+{
+    /// the period of time that the frequency refers to, e.g. "week" for weekly.
+    fn period(self) -> &'static str
+    {
+        match self
+        {
+            Frequency::daily       => "day",
+            Frequency::workdays    => "working day",
+            Frequency::weekly      => "week",
+            Frequency::fortnightly => "fortnight",
+            Frequency::monthly     => "month",
+            Frequency::quarterly   => "quarter",
+            Frequency::yearly      => "year",
+            Frequency::once        => "one-off",
+        }
+    }
+
+    /// how many months one of these periods makes up, e.g. 12 for yearly,
+    /// using the values in `config` where they are set.
+    ///
+    /// An amount per `a` is projected onto `b` by scaling it by
+    /// `b.to_months_factor() / a.to_months_factor()`.
+    fn to_months_factor(self, config: &Config) -> Ratio
+    {
+        // a year is 365 days, or 365/7 weeks, unless the config says otherwise.
+        let days     = config.days_per_month.map_or(Ratio::new(365, 12), Ratio::from_f64);
+        let weeks    = config.weeks_per_month.map_or(Ratio::new(365, 7 * 12), Ratio::from_f64);
+        let workdays = config.workdays_per_month.map_or(weeks * Ratio::whole(5), Ratio::from_f64);
+
+        match self
+        {
+            Frequency::daily       => Ratio::whole(1) / days,
+            Frequency::workdays    => Ratio::whole(1) / workdays,
+            Frequency::weekly      => Ratio::whole(1) / weeks,
+            Frequency::fortnightly => Ratio::whole(2) / weeks,
+            Frequency::monthly     => Ratio::whole(1),
+            Frequency::quarterly   => Ratio::whole(3),
+            Frequency::yearly      => Ratio::whole(12),
+
+            // a one-off is counted in full in the month it happens, and not at all in others.
+            Frequency::once        => Ratio::whole(1),
+        }
+    }
+}
+
+
+/// A number of days, weeks, months and so on, that a report projects onto,
+/// e.g. `3m` for a quarter, or `2y`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Period
+{
+    count: u32,
+    unit: Frequency,
+}
+
+
+impl From<Frequency> for Period
+{
+    fn from(unit: Frequency) -> Self
+    {
+        Period { count: 1, unit }
+    }
+}
+
+
+impl FromStr for Period
+{
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, unit) = s.split_at(split);
+
+        // a frequency on its own, e.g. weekly, is one of that period.
+        if let Ok(unit) = Frequency::from_str(s)
+        {
+            return Ok(Period::from(unit));
+        }
+
+        let unit = match unit.to_lowercase().as_str()
+        {
+            "d" => Frequency::daily,
+            "w" => Frequency::weekly,
+            "f" => Frequency::fortnightly,
+            "m" => Frequency::monthly,
+            "q" => Frequency::quarterly,
+            "y" => Frequency::yearly,
+            _   => return Err(format!("{} is not a period; use a frequency, or a number and one of d, w, f, m, q or y, e.g. 3m", s)),
+        };
+
+        // no number is one of them, e.g. w for a week.
+        match if count.is_empty() { Ok(1) } else { u32::from_str(count) }
+        {
+            Ok(count) if count > 0 => Ok(Period { count, unit }),
+            _                      => Err(format!("{} is not a period; the number must be a whole number from 1 up, e.g. 3m", s)),
+        }
+    }
+}
+
+
+impl Period
+{
+    /// how many months the period makes up; see `Frequency::to_months_factor`.
+    fn to_months_factor(self, config: &Config) -> Ratio
+    {
+        self.unit.to_months_factor(config) * Ratio::whole(i64::from(self.count))
+    }
+
+    /// the date `n` of these periods after `date`; None for working days and
+    /// one-offs, which don't step evenly through the calendar, and for dates
+    /// too far ahead to hold.
+    fn after(self, date: NaiveDate, n: u32) -> Option<NaiveDate>
+    {
+        let count = self.count.saturating_mul(n);
+        let days = |d: u32| chrono::Duration::try_days(i64::from(d) * i64::from(count)).and_then(|days| date.checked_add_signed(days));
+        let months = |m: u32| date.checked_add_months(Months::new(m.saturating_mul(count)));
+
+        match self.unit
+        {
+            Frequency::daily       => days(1),
+            Frequency::weekly      => days(7),
+            Frequency::fortnightly => days(14),
+            Frequency::monthly     => months(1),
+            Frequency::quarterly   => months(3),
+            Frequency::yearly      => months(12),
+            Frequency::workdays
+            | Frequency::once      => None,
+        }
+    }
+
+    /// the first day of the period that a report on `on` covers, and the day
+    /// after its last; a period of months starts on the 1st of `on`'s month,
+    /// and a shorter one on `on`. Working days and one-offs cover the month.
+    fn span(self, on: NaiveDate) -> (NaiveDate, NaiveDate)
+    {
+        let start = match self.is_months()
+        {
+            true  => on.with_day(1).unwrap_or(on),
+            false => on,
+        };
+
+        let end = self.after(start, 1)
+            .unwrap_or_else(|| on.with_day(1).unwrap_or(on) + Months::new(1));
+
+        return (start.min(end), end);
+    }
+
+    /// true if the period is a whole number of months.
+    fn is_months(self) -> bool
+    {
+        matches!(self.unit, Frequency::monthly | Frequency::quarterly | Frequency::yearly)
+    }
+
+    /// the period in words, e.g. "week" or "3 months".
+    fn describe(self) -> String
+    {
+        match self.count
+        {
+            1     => self.unit.period().to_string(),
+            count => format!("{} {}s", count, self.unit.period()),
+        }
+    }
+}
+
+
+impl Transaction
+{
+    /// the fraction of the year that this transaction happens in; 1 unless it
+    /// skips some months.
+    fn active_fraction(&self) -> Ratio
+    {
+        let skipped = (1..=12).filter(|m| self.skip_months.contains(m)).count();
+        return Ratio::new(12 - skipped as i128, 12);
+    }
+
+    /// how many months one of this transaction's repeats makes up; its
+    /// frequency's, times `every`.
+    fn months_factor(&self, config: &Config) -> Ratio
+    {
+        self.freq.to_months_factor(config) * Ratio::whole(i64::from(self.every.unwrap_or(1)))
+    }
+
+    /// the part of the amount that is kept; less the tax, for a gross income.
+    fn take_home(&self, config: &Config) -> Ratio
+    {
+        match self.gross && self.add_type == AddType::income
+        {
+            true  => Ratio::from_f64(1.0 - config.tax_rate.unwrap_or(0.0) / 100.0),
+            false => Ratio::whole(1),
+        }
+    }
+
+    /// false if the amount is below `min` or above `max`.
+    fn range_contains_amount(&self) -> bool
+    {
+        match self.amount
+        {
+            Amount::fixed(amount) => self.min.is_none_or(|min| min <= amount) && self.max.is_none_or(|max| amount <= max),
+            Amount::percent(_)    => true,
+        }
+    }
+
+    /// the amount of one of these transactions, working out a percentage of
+    /// `monthly_income`.
+    fn money(&self, monthly_income: Money, config: &Config) -> Money
+    {
+        self.amount.resolve(monthly_income, self.months_factor(config))
+    }
+
+    /// the account this transaction uses, or for a transfer, the accounts it
+    /// is between, e.g. "savings -> everyday".
+    fn describe_accounts(&self) -> String
+    {
+        let account = self.account.clone().unwrap_or_default();
+
+        match self.to_account
+        {
+            Some(ref to) => format!("{} -> {}", account, to),
+            None         => account,
+        }
+    }
+
+    /// how often this transaction happens, e.g. "weekly", or "every 6 weeks".
+    fn describe_freq(&self) -> String
+    {
+        match self.every.filter(|&n| n > 1)
+        {
+            Some(n) => format!("every {} {}s", n, self.freq.period()),
+            None    => self.freq.to_string(),
+        }
+    }
+
+    /// true if this transaction happens in the month containing `date`; it
+    /// isn't paused, starts in or before that month, and ends in or after it
+    /// (or, for a one-off, happens in that month).
+    fn active_in(&self, date: NaiveDate) -> bool
+    {
+        let month = (date.year(), date.month());
+
+        if self.paused
+        {
+            return false;
+        }
+
+        if self.freq == Frequency::once
+        {
+            return self.start_date.is_some_and(|on| (on.year(), on.month()) == month);
+        }
+
+        self.start_date.is_none_or(|start| (start.year(), start.month()) <= month)
+            && self.end_date.is_none_or(|end| (end.year(), end.month()) >= month)
+    }
+
+    /// the part of the month containing `date` that this transaction is active
+    /// for, by days; less than 1 only in a month that it starts or ends
+    /// partway through. A one-off is all there in its month.
+    fn month_fraction(&self, date: NaiveDate) -> Ratio
+    {
+        let first = date.with_day(1).unwrap_or(date);
+        let next = first + Months::new(1);
+        if self.freq == Frequency::once
+        {
+            return Ratio::whole(1);
+        }
+
+        let from = self.start_date.map_or(first, |start| start.max(first));
+        let until = self.end_date.and_then(|end| end.succ_opt()).map_or(next, |end| end.min(next));
+
+        let days = (until - from).num_days().max(0);
+        return Ratio::new(i128::from(days), i128::from((next - first).num_days()));
+    }
+
+    /// true if this one-off happens from the first of `span` up to (but not
+    /// on) the second, and isn't paused.
+    fn happens_within(&self, span: (NaiveDate, NaiveDate)) -> bool
+    {
+        !self.paused && self.start_date.is_some_and(|on| span.0 <= on && on < span.1)
+    }
+
+    /// the next date, on or after `today`, that this transaction is due; if it
+    /// has a due day, and happens monthly, or is a one-off that hasn't happened yet.
+    ///
+    /// Due days past the end of a month fall on its last day, and skipped
+    /// months are passed over. Those repeating every few months are only
+    /// known to be due if they have a start date to count from.
+    fn next_due(&self, today: NaiveDate) -> Option<NaiveDate>
+    {
+        if self.paused
+        {
+            return None;
+        }
+
+        if self.freq == Frequency::once
+        {
+            return self.start_date.filter(|on| *on >= today);
+        }
+
+        let day = self.due_day?;
+        if self.freq != Frequency::monthly
+        {
+            return None;
+        }
+
+        let every = self.every.unwrap_or(1);
+        let start = match self.start_date
+        {
+            Some(start)       => start.with_day(1)?,
+            None if every > 1 => return None,
+            None              => today.with_day(1)?,
+        };
+
+        let this_month = today.with_day(1)?.max(start);
+        let since_start = (this_month.year() - start.year()) * 12 + this_month.month() as i32 - start.month() as i32;
+        let behind = (every as i32 - since_start % every as i32) % every as i32;
+
+        (0..13).map(|i| this_month + Months::new(behind as u32 + i * every))
+            .filter(|month| !self.skip_months.contains(&(month.month() as u8)))
+            .map(|month|
+            {
+                let last_day = (month + Months::new(1)).pred_opt().map_or(31, |d| d.day());
+                month.with_day(u32::from(day).min(last_day)).unwrap_or(month)
+            })
+            .take_while(|due| self.end_date.is_none_or(|end| *due <= end))
+            .find(|due| *due >= today && self.start_date.is_none_or(|start| *due >= start))
+    }
+
+    /// for a transaction that happens every few months (quarterly, yearly, or
+    /// monthly `--every` N) and has a start date to count from, whether it
+    /// happens in `month`; none for anything else, which is spread evenly
+    /// over the months instead.
+    fn falls_in(&self, month: NaiveDate) -> Option<bool>
+    {
+        let months = match self.freq
+        {
+            Frequency::monthly   => 1,
+            Frequency::quarterly => 3,
+            Frequency::yearly    => 12,
+            _                    => return None,
+        } * self.every.unwrap_or(1) as i32;
+
+        let start = self.start_date.filter(|_| months > 1)?;
+        let since_start = (month.year() - start.year()) * 12 + month.month() as i32 - start.month() as i32;
+
+        return Some(since_start.rem_euclid(months) == 0);
+    }
+}
+
+
+/// A transaction from the ledger, with its amount projected onto the report period.
+/// Expenses have a negative amount.
+struct Row<'a>
+{
+    transaction: &'a Transaction,
+    amount: Money,
+}
+
+
+/// The figures that make up a report, projected onto a single period.
+struct Summary<'a>
+{
+    /// every transaction in the ledger.
+    rows: Vec<Row<'a>>,
+
+    /// net income for the period.
+    total: Money,
+
+    /// total income for the period.
+    income: Money,
+
+    /// income by category (its source), and income without one.
+    income_sources: HashMap<String, Money>,
+    other_income: Money,
+
+    /// expenses by category, and expenses without one.
+    breakdown: HashMap<String, Money>,
+    other_expenses: Money,
+
+    /// expenses by account, and expenses without one.
+    coverage: HashMap<String, Money>,
+    other_alloc: Money,
+
+    /// incomes by the account they are paid into.
+    account_income: HashMap<String, Money>,
+
+    /// the part of the expenses that are only estimates.
+    estimated_expenses: Money,
+
+    /// the part of the expenses that could be cut.
+    discretionary_expenses: Money,
+
+    /// the tax taken off gross incomes.
+    tax: Money,
+
+    /// your own part of the expenses; all of each, except for shared ones.
+    my_expenses: Money,
+
+    /// the net if every amount that varies comes in at its best (least
+    /// expense, most income), and at its worst.
+    optimistic: Money,
+    pessimistic: Money,
+
+    /// expenses from each account (or `(no account)`) into each category (or
+    /// `(other)`), with splits taking their share.
+    flows: BTreeMap<(String, String), Money>,
+}
+
+
+/// Projects each transaction in the ledger onto the period `per`, and totals
+/// them up.
+fn summarise<'a>(ledger: &'a Ledger, per: Frequency, config: &Config) -> Summary<'a>
+{
+    summarise_split(ledger, Period::from(per), Period::from(per), None, Local::now().date_naive(), config)
+}
+
+
+/// Like `summarise`, but projects incomes onto `income_per` and expenses onto
+/// `expense_per`, and rounds each projected amount to the nearest `round_to`
+/// (or to the cent) before adding it up, so the rows add up to the total.
+///
+/// When the two periods differ, the total mixes them, and should be labelled as such.
+///
+/// Transactions that haven't started by the month containing `on`, or have
+/// already ended, are left out. The rows are in order of name.
+fn summarise_split<'a>(ledger: &'a Ledger, income_per: Period, expense_per: Period, round_to: Option<Money>, on: NaiveDate, config: &Config) -> Summary<'a>
+{
+    let mut summary = Summary
+    {
+        rows: Vec::new(),
+        total: Money::default(),
+        income: Money::default(),
+        income_sources: HashMap::new(),
+        other_income: Money::default(),
+        breakdown: HashMap::new(),
+        other_expenses: Money::default(),
+        coverage: HashMap::new(),
+        other_alloc: Money::default(),
+        estimated_expenses: Money::default(),
+        discretionary_expenses: Money::default(),
+        tax: Money::default(),
+        my_expenses: Money::default(),
+        optimistic: Money::default(),
+        pessimistic: Money::default(),
+        flows: BTreeMap::new(),
+        account_income: HashMap::new(),
+    };
+
+    let monthly_income = monthly_income(ledger, on, config);
+    let increment = round_to.unwrap_or(Money { mills: 10 });
+
+    for (_, transaction) in sorted(ledger).into_iter().filter(|(_, t)| t.freq == Frequency::once || t.active_in(on))
+    {
+        let per = match transaction.add_type
+        {
+            AddType::income                      => income_per,
+            AddType::expense | AddType::transfer => expense_per,
+        };
+
+        // transactions that skip some months are averaged out over the year,
+        // and gross incomes have the tax taken off. A one-off counts once, in
+        // full, if it happens in the period.
+        let multiplier = match transaction.freq
+        {
+            Frequency::once if !transaction.happens_within(per.span(on)) => continue,
+            Frequency::once => Ratio::whole(1),
+            _               => per.to_months_factor(config) / transaction.months_factor(config) * transaction.active_fraction(),
+        };
+        let before_tax = transaction.money(monthly_income, config).times(multiplier);
+        let multiplier = multiplier * transaction.take_home(config);
+        let money = transaction.money(monthly_income, config).times(multiplier);
+        let money = money.round_to(increment);
+        summary.tax += before_tax.round_to(increment) - money;
+
+        // the ends of the range, projected the same way; the amount itself if there isn't one.
+        let bound = |bound: Option<Money>| bound.map_or(money, |b|
+        {
+            let b = b.times(multiplier);
+            b.round_to(increment)
+        });
+
+        match transaction.add_type
+        {
+            AddType::income =>
+            {
+                summary.optimistic += bound(transaction.max);
+                summary.pessimistic += bound(transaction.min);
+            },
+
+            AddType::expense =>
+            {
+                summary.optimistic -= bound(transaction.min);
+                summary.pessimistic -= bound(transaction.max);
+            },
+
+            AddType::transfer => (),
+        }
+
+        let amount = match transaction.add_type
+        {
+            AddType::income =>
+            {
+                match transaction.category
+                {
+                    Some(ref s) => add_to_label(&mut summary.income_sources, s, money),
+                    None        => summary.other_income += money,
+                }
+
+                if let Some(ref account) = transaction.account
+                {
+                    add_to_label(&mut summary.account_income, account, money);
+                }
+
+                summary.income += money;
+                money
+            },
+
+            AddType::expense =>
+            {
+                // splits take their share, and the rest stays in the transaction's own category.
+                let account = transaction.account.clone().unwrap_or_else(|| "(no account)".to_string());
+                let mut rest = money;
+                for split in &transaction.splits
+                {
+                    let share = money.scaled(split.percent / 100.0).round_to(Money { mills: 10 });
+                    add_to_label(&mut summary.breakdown, &split.category, share);
+                    *summary.flows.entry((account.clone(), split.category.clone())).or_default() += share;
+                    rest -= share;
+                }
+
+                match transaction.category
+                {
+                    Some(ref s) => add_to_label(&mut summary.breakdown, s, rest),
+                    None        => summary.other_expenses += rest,
+                }
+
+                *summary.flows.entry((account, transaction.category.clone().unwrap_or_else(|| "(other)".to_string()))).or_default() += rest;
+
+                match transaction.account
+                {
+                    Some(ref s) => add_to_label(&mut summary.coverage, s, money),
+                    None        => summary.other_alloc += money,
+                }
+
+                if transaction.estimated
+                {
+                    summary.estimated_expenses += money;
+                }
+
+                if transaction.discretionary
+                {
+                    summary.discretionary_expenses += money;
+                }
+
+                // rounded like the rest, so that it adds up with what's shown.
+                let share = Ratio::from_f64(transaction.share.unwrap_or(100.0) / 100.0);
+                summary.my_expenses += money.times(share).round_to(increment);
+
+                -money
+            },
+
+            // a transfer doesn't change the net; the account it comes from
+            // needs to cover it, and the one it goes into has that much less to cover.
+            AddType::transfer =>
+            {
+                for (account, money) in transaction.account.iter().map(|a| (a, money)).chain(transaction.to_account.iter().map(|a| (a, -money)))
+                {
+                    add_to_label(&mut summary.coverage, account, money);
+                }
+
+                Money::default()
+            },
+        };
+
+        summary.total += amount;
+        summary.rows.push(Row { transaction, amount });
+    }
+
+    return summary;
+}
+
+
+/// the total income per month in `ledger`, as of `date`, for working out
+/// percentages of it; averaged over the year like the report.
+fn monthly_income(ledger: &Ledger, date: NaiveDate, config: &Config) -> Money
+{
+    ledger.values()
+        .filter(|t| t.add_type == AddType::income && t.active_in(date))
+        .filter_map(|t| t.amount.money().map(|money| money.times(t.take_home(config) * t.active_fraction() / t.months_factor(config))))
+        .fold(Money::default(), |total, money| total + money)
+}
+
+
+/// Adds `money` to the total for `label` in `totals`.
+///
+/// Labels that only differ in case ("Food" and "food") are added up together,
+/// under whichever spelling sorts first.
+fn add_to_label(totals: &mut HashMap<String, Money>, label: &str, money: Money)
+{
+    let existing = totals.keys()
+        .find(|k| k.to_lowercase() == label.to_lowercase())
+        .cloned();
+
+    match existing
+    {
+        Some(key) if key.as_str() <= label =>
+        {
+            *totals.entry(key).or_default() += money;
+        },
+
+        Some(key) =>
+        {
+            let total = totals.remove(&key).unwrap_or_default() + money;
+            totals.insert(label.to_string(), total);
+        },
+
+        None =>
+        {
+            totals.insert(label.to_string(), money);
+        },
+    }
+}
+
+
+/// Generates a report for a month, extrapolating the values specified in the ledger.
+///
+/// The report has three sections; a table, a "breakdown", and a "coverage" section.
+///
+/// The table is simply a table displaying all the information about each transaction
+/// in the ledger, with costs projected onto one month. For example, a yearly income
+/// of $24k would be displayed as `2000.00`.
+///
+/// The breakdown shows the total expenses by category. You can specify the category
+/// of an expense using the `--category` option of `pfr add`. 
+///
+/// The coverage section shows how much money you need in each of your accounts
+/// in order to cover the months expenses. You can specify the account that each
+/// expense is drawn from using the `--account` option of `pfr add`.
+fn report(rc: ReportCommand) -> Result<()>
+{
+    report_on(&load_current_ledger()?, &rc, serde_json::Map::new())
+}
+
+
+/// Prints the report for `ledger`; see `report`. With `--json`, the fields in
+/// `extra` are added to the report's object.
+fn report_on(ledger: &Ledger, rc: &ReportCommand, extra: serde_json::Map<String, serde_json::Value>) -> Result<()>
+{
+    let mut config = rc.load_config()?;
+    config.apply_tax_brackets(ledger, rc.date());
+    let painter = Painter::new(rc.color);
+
+    let (income_per, expense_per) = rc.pers(&config)?;
+
+    // with `--only`, `--tag`, `--category` or `--account`, the rest is left out
+    // before anything is added up; percentages of income are worked out
+    // first, while it is all there. A category matches a split's too.
+    let is = |label: &Option<String>, filter: &str| label.as_ref().is_some_and(|label| same_label(label, filter));
+    let income = monthly_income(ledger, rc.date(), &config);
+    let ledger: Ledger = ledger.iter()
+        .filter(|(_, t)| rc.only.is_none_or(|only| t.add_type == only))
+        .filter(|(_, t)| rc.tag.as_ref().is_none_or(|tag| t.tags.iter().any(|t| same_label(t, tag))))
+        .filter(|(_, t)| rc.category.as_ref().is_none_or(|c| is(&t.category, c) || t.splits.iter().any(|s| same_label(&s.category, c))))
+        .filter(|(_, t)| rc.account.as_ref().is_none_or(|a| is(&t.account, a) || is(&t.to_account, a)))
+        .map(|(id, t)|
+        {
+            let money = t.money(income, &config);
+            let money = if rc.prorate { money.times(t.month_fraction(rc.date())) } else { money };
+            (id.clone(), Transaction { amount: Amount::fixed(money), ..t.clone() })
+        })
+        .collect();
+
+    let mut summary = summarise_split(&ledger, income_per, expense_per, rc.round_to, rc.date(), &config);
+
+    if let Some(sort) = rc.sort
+    {
+        // stable, so that ties stay in order of name.
+        summary.rows.sort_by(|a, b| match sort
+        {
+            ReportSort::amount   => a.amount.cmp(&b.amount),
+            ReportSort::name     => a.transaction.name.cmp(&b.transaction.name),
+            ReportSort::category => a.transaction.category.cmp(&b.transaction.category),
+        });
+    }
+
+    if rc.descending
+    {
+        summary.rows.reverse();
+    }
+
+    if rc.check
+    {
+        println!("{}", summary.total.signed().trim());
+
+        return match summary.total < rc.threshold
+        {
+            true  => Err(Error::NetIsBelowThreshold),
+            false => Ok(()),
+        };
+    }
+
+    let mixed = income_per != expense_per;
+    let title = match (mixed, income_per.count, income_per.unit)
+    {
+        (false, 1, Frequency::monthly) => "Monthly Report".to_string(),
+        (false, 1, Frequency::yearly)  => "Yearly Report".to_string(),
+        (false, 1, _)                  => format!("Report per {}", income_per.describe()),
+        (false, _, _)                  => format!("Report over {}", income_per.describe()),
+        (true, _, _)                   => format!("Report (incomes per {}, expenses per {})", income_per.describe(), expense_per.describe()),
+    };
+
+    // a pay period is the same length as its frequency's, but is named as one.
+    let title = match (mixed, rc.per, config.pay_period(rc.date()))
+    {
+        (false, Some(Per::payday), Some((start, end))) => format!("Report per pay period ({} to {})", format_date(start), format_date(end)),
+        (false, Some(Per::payday), None)               => "Report per pay period".to_string(),
+        _                                              => title,
+    };
+
+    let title = match rc.month
+    {
+        Some(month) if !mixed => format!("Report for {}", month.format("%B %Y")),
+        _                     => title,
+    };
+
+    if json_output()
+    {
+        let mut report = json_report(&title, &summary);
+        if let serde_json::Value::Object(ref mut fields) = report
+        {
+            fields.extend(extra);
+        }
+
+        println!("{}", serde_json::to_string_pretty(&report).map_err(Error::DuringSerialisation)?);
+        return Ok(());
+    }
+
+    if let Some(name) = &rc.template
+    {
+        let template = fs::read_to_string(get_path("templates")?.join(name))
+            .map_err(|e| Error::WhileReadingTemplate(name.clone(), e.to_string()))?;
+
+        let page = render_template(&template, &mut vec![json_report(&title, &summary)])
+            .map_err(|e| Error::WhileReadingTemplate(name.clone(), e))?;
+
+        print!("{}", page);
+        return Ok(());
+    }
+
+    if let Some(path) = &rc.html
+    {
+        fs::write(path, html_report(&title, &summary)).map_err(Error::WhileWritingReport)?;
+        println!("Wrote the report to {}", path.display());
+        return Ok(());
+    }
+
+    if rc.format == ReportFormat::html
+    {
+        print!("{}", html_report(&title, &summary));
+        return Ok(());
+    }
+
+    if rc.format == ReportFormat::dot || rc.format == ReportFormat::sankey
+    {
+        print_flows(rc.format, &summary);
+        return Ok(());
+    }
+
+    if rc.format == ReportFormat::markdown
+    {
+        print!("{}", markdown_report(&title, &summary));
+        return Ok(());
+    }
+
+    if rc.format == ReportFormat::csv
+    {
+        let projected = match (mixed, income_per == Period::from(Frequency::monthly))
+        {
+            (false, true)  => "monthly".to_string(),
+            (false, false) => format!("per {}", income_per.describe()),
+            (true, _)      => "projected".to_string(),
+        };
+
+        print_rows(ExportFormat::csv, &projected, summary.rows.iter().map(|row| (row.transaction, Some(row.amount))));
+        return Ok(());
+    }
+
+    println!("{}", title);
+    print_last_modified();
+    println!();
+
+    // with `--show-annual`, each amount is followed by what it comes to over
+    // twelve months, projected (and rounded) from the transaction itself.
+    let yearly = summarise_split(&ledger, Period { count: 12, unit: Frequency::monthly }, Period { count: 12, unit: Frequency::monthly }, rc.round_to, rc.date(), &config);
+    let annual = |rows: &[&Row]| -> Money
+    {
+        rows.iter()
+            .filter_map(|row| yearly.rows.iter().find(|r| ptr::eq(r.transaction, row.transaction)))
+            .map(|r| &r.amount)
+            .sum()
+    };
+
+    let annual = |rows: &[&Row]| match rc.show_annual
+    {
+        true  => painter.paint(&format!("{:<12}", annual(rows).signed()), annual(rows)),
+        false => String::new(),
+    };
+
+    // the columns are as wide as what's in them; on a terminal, the names are
+    // cut short so that the table fits.
+    let yearly_width = if rc.show_annual { 12 } else { 0 };
+    let cat_width = column_width(summary.rows.iter().filter_map(|row| row.transaction.category.as_deref()), 10, 20);
+    let free = terminal_width().saturating_sub(12 + yearly_width + cat_width + 12);
+    let name_width = column_width(summary.rows.iter().map(|row| row.transaction.name.as_str()), 20, free / 2);
+
+    let rule = "-".repeat(2 * name_width + 12 + yearly_width + cat_width + 9);
+
+    if !rc.compact
+    {
+        let yearly = if rc.show_annual { format!("{:<12}", "YEARLY") } else { String::new() };
+        println!("{:<w$}{:<w$}{:<12}{}{:<c$}{:<8}", "INCOME", "EXPENDITURE", "VALUE", yearly, "CATEGORY", "ACCOUNT", w = name_width, c = cat_width);
+        println!("{}", rule);
+    }
+
+    let print_row = |row: &Row|
+    {
+        let transaction = row.transaction;
+
+        let mut income = String::new();
+        let mut expend = String::new();
+        let cat    = fit(&transaction.category.clone().unwrap_or_default(), cat_width - 1);
+        let accnt  = transaction.describe_accounts();
+
+        // estimates are marked with a `~`.
+        let name = match transaction.estimated
+        {
+            true  => format!("{}~", transaction.name),
+            false => transaction.name.clone(),
+        };
+
+        match transaction.add_type
+        {
+            AddType::income                      => income = fit(&name, name_width - 1),
+            AddType::expense | AddType::transfer => expend = fit(&name, name_width - 1),
+        }
+
+        let amount = painter.paint(&format!("{:<12}", row.amount.signed()), row.amount);
+        println!("{:<w$}{:<w$}{}{}{:<c$}{:<8}", income, expend, amount, annual(&[row]), cat, accnt, w = name_width, c = cat_width);
+    };
+
+    match rc.group_by
+    {
+        _ if rc.compact => (),
+
+        None => summary.rows.iter().for_each(print_row),
+
+        Some(group_by) =>
+        {
+            for (name, rows) in groups(summary.rows.iter(), group_by, |row| row.transaction)
+            {
+                println!("{}:", name);
+                rows.iter().for_each(|row| print_row(row));
+
+                let subtotal: Money = rows.iter().map(|row| &row.amount).sum();
+                let subtotal = painter.paint(&format!("{:<12}", subtotal.signed()), subtotal);
+                println!("{:<w$}{:<w$}{}{}\n", "", "subtotal: ", subtotal, annual(&rows), w = name_width);
+            }
+        }
+    }
+
+    if !rc.compact
+    {
+        println!("{}", rule);
+    }
+
+    let total = painter.bold(&painter.paint(&format!("{:<12}", summary.total.signed()), summary.total));
+    match mixed
+    {
+        false =>
+        {
+            println!("{:<w$}{}{}{}", "", painter.bold(&format!("{:<w$}", "TOTAL: ", w = name_width)), total, annual(&summary.rows.iter().collect::<Vec<_>>()), w = name_width);
+
+            // how much of the income is left over, and what that comes to day to day.
+            if summary.income.mills > 0
+            {
+                let rate = summary.total.mills as f64 / summary.income.mills as f64 * 100.0;
+                let per = |freq: Frequency| summary.total.times(freq.to_months_factor(&config) / income_per.to_months_factor(&config));
+                println!("Savings rate: {:.1}% (net {} per day, {} per week)", rate, per(Frequency::daily).plain(), per(Frequency::weekly).plain());
+            }
+
+            println!();
+        },
+
+        true  =>
+        {
+            println!("{:<w$}{}{}{}", "", painter.bold(&format!("{:<w$}", "TOTAL (mixed): ", w = name_width)), total, annual(&summary.rows.iter().collect::<Vec<_>>()), w = name_width);
+            println!("note: the total adds incomes per {} to expenses per {}, so it is not a real net\n", income_per.describe(), expense_per.describe());
+        }
+    }
+
+    if let Some(goal) = rc.savings_goal.or(config.savings_goal.map(Money::from_dollars))
+    {
+        match summary.total >= goal
+        {
+            true  => println!("Goal: {} — on track, surplus {}\n", goal.plain(), (summary.total - goal).plain()),
+            false => println!("Goal: {} — {}\n", goal.plain(), painter.warn(&format!("short by {}", (goal - summary.total).plain()))),
+        }
+    }
+
+    // shares that come to more than the whole surplus are scaled down to fit.
+    if !mixed && summary.total.mills > 0 && !config.surplus_split.is_empty()
+    {
+        let percents = config.surplus_split.iter().map(|share| share.percent.max(0.0)).sum::<f64>().max(100.0);
+        let mut left = summary.total;
+
+        println!("Suggested use of the surplus:");
+        for share in &config.surplus_split
+        {
+            let fraction = share.percent.max(0.0) / percents;
+            let value = summary.total.scaled(fraction).round_to(rc.round_to.unwrap_or(Money { mills: 10 }));
+            left -= value;
+
+            println!("{:<10} -> {:<16}{:>5.1}%", value, share.to, fraction * 100.0);
+        }
+
+        if left.mills != 0
+        {
+            println!("{:<10}    {:<16}", left, "(left over)");
+        }
+
+        println!();
+    }
+
+    // each category's share of the income, and of everything spent.
+    let expenses = summary.breakdown.values().sum::<Money>() + summary.other_expenses;
+    let share = |value: Money, of: Money| match of.mills
+    {
+        0     => 0.0,
+        mills => value.mills as f64 / mills as f64 * 100.0,
+    };
+
+    // bars, scaled to the biggest amount in the section, fill what's left of the line.
+    let bars = match rc.bars
+    {
+        ColorChoice::auto   => std::io::stdout().is_terminal(),
+        ColorChoice::always => true,
+        ColorChoice::never  => false,
+    };
+
+    let bar_width = terminal_width().saturating_sub(46).max(10);
+    let bar = |value: Money, amounts: &[(&str, Money)]| match bars
+    {
+        true  =>
+        {
+            let biggest = amounts.iter().map(|(_, value)| value.mills.unsigned_abs()).max().unwrap_or(0).max(1);
+            format!(" {}", draw_bar(value.mills.unsigned_abs() as f64 / biggest as f64, bar_width))
+        },
+        false => String::new(),
+    };
+
+    println!("{:<26}{:>8}{:>10}", "Breakdown:", "income", "expenses");
+    let breakdown = biggest_first(&summary.breakdown, ("(other)", summary.other_expenses));
+    for &(name, value) in &breakdown
+    {
+        println!("{:<16}{:10}{:>7.1}%{:>9.1}%{}", name, value, share(value, summary.income), share(value, expenses), bar(value, &breakdown));
+    }
+
+    println!();
+
+    // with only a few expenses, the table already shows them all.
+    let mut largest: Vec<&Row> = summary.rows.iter().filter(|row| row.transaction.add_type == AddType::expense).collect();
+    let top = rc.top.unwrap_or(5);
+
+    if top > 0 && (rc.top.is_some() || largest.len() > top)
+    {
+        largest.sort_by_key(|row| row.amount);
+
+        println!("Largest expenses:");
+        for row in largest.into_iter().take(top)
+        {
+            println!("{:<20}{:10}{:>9.1}%", row.transaction.name, -row.amount, share(-row.amount, expenses));
+        }
+
+        println!();
+    }
+
+    // only worth showing once some income has a source.
+    if !summary.income_sources.is_empty()
+    {
+        println!("Income by source:");
+        for (name, value) in biggest_first(&summary.income_sources, ("(other)", summary.other_income))
+        {
+            println!("{:<16}{:10}  {:>5.1}%", name, value, share(value, summary.income));
+        }
+
+        println!();
+    }
+
+    println!("Coverage:");
+    let coverage = biggest_first(&summary.coverage, ("(unallocated)", summary.other_alloc));
+    for &(name, value) in &coverage[..coverage.len() - 1]
+    {
+        println!("{:<10} -> {:<16}{}", value, name, bar(value, &coverage));
+    }
+
+    // calculate total coverage
+    let coverage_total: Money = summary.coverage.values().sum();
+
+    println!("{:<10}    {:<16}{}", summary.other_alloc, "(unallocated)", bar(summary.other_alloc, &coverage));
+    println!("{:<10}    {:<10}", coverage_total,      "(total)");
+
+    // only once some income says which account it is paid into.
+    if !summary.account_income.is_empty()
+    {
+        let mut accounts: Vec<&String> = summary.account_income.keys().chain(summary.coverage.keys()).collect();
+        accounts.sort();
+        accounts.dedup();
+
+        println!("\nAccounts:");
+        println!("{:<16}{:<12}{:<12}{:<12}", "", "IN", "OUT", "LEFT");
+        for account in accounts
+        {
+            let incoming = summary.account_income.get(account).cloned().unwrap_or_default();
+            let outgoing = summary.coverage.get(account).cloned().unwrap_or_default();
+            let left = incoming - outgoing;
+
+            match left.mills < 0
+            {
+                true  => println!("{:<16}{:<12}{:<12}{:<12}{}", account, incoming.signed(), outgoing.signed(), left.signed(), painter.warn(&format!("needs a top-up of {}", (-left).plain()))),
+                false => println!("{:<16}{:<12}{:<12}{:<12}", account, incoming.signed(), outgoing.signed(), left.signed()),
+            }
+        }
+    }
+
+    if summary.estimated_expenses != Money::default()
+    {
+        let expenses = summary.income - summary.total;
+
+        println!("\nEstimates:");
+        println!("{:<10}    {:<10}", expenses - summary.estimated_expenses, "fixed");
+        println!("{:<10}    {:<10}", summary.estimated_expenses,             "estimated");
+    }
+
+    let expenses = summary.income - summary.total;
+    if summary.discretionary_expenses != Money::default()
+    {
+        println!("\nSpending:");
+        println!("{:<10}    {:<10}", expenses - summary.discretionary_expenses, "essential");
+        println!("{:<10}    {:<10}", summary.discretionary_expenses,             "discretionary");
+    }
+
+    if summary.my_expenses != expenses
+    {
+        println!("\nMy share:");
+        println!("{:<10}    {:<10}", (-expenses).signed(),                             "household expenses");
+        println!("{:<10}    {:<10}", (-summary.my_expenses).signed(),                  "my expenses");
+        println!("{:<10}    {:<10}", (summary.income - summary.my_expenses).signed(), "my net");
+    }
+
+    if summary.tax != Money::default()
+    {
+        println!("\nTax:");
+        println!("{:<10}    {:<10}", (summary.income + summary.tax).signed(), "gross income");
+        println!("{:<10}    {:<10}", (-summary.tax).signed(),                 "tax");
+        println!("{:<10}    {:<10}", summary.income.signed(),                 "take-home income");
+        println!("{:<10}    {:<10}", summary.total.signed(),                  "after-tax net");
+        println!("{:>9}     {:<10}", format!("{:.1}%", config.tax_rate.unwrap_or(0.0)), "effective rate");
+    }
+
+    if summary.optimistic != summary.pessimistic
+    {
+        println!("\nRange:");
+        println!("{:<10}    {:<10}", summary.optimistic.signed(),  "optimistic");
+        println!("{:<10}    {:<10}", summary.total.signed(),       "expected");
+        println!("{:<10}    {:<10}", summary.pessimistic.signed(), "pessimistic");
+    }
+
+    Ok(())
+}
+
+
+/// escapes `text` for use in HTML.
+fn escape_html(text: &str) -> String
+{
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+/// the width of the terminal, from `$COLUMNS`; 80 if it isn't set.
+fn terminal_width() -> usize
+{
+    env::var("COLUMNS").ok().and_then(|columns| usize::from_str(&columns).ok()).unwrap_or(80)
+}
+
+
+/// a bar `fraction` of `width` characters long, to an eighth of a character.
+fn draw_bar(fraction: f64, width: usize) -> String
+{
+    const PARTS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+    let eighths = (fraction.clamp(0.0, 1.0) * width as f64 * 8.0).round() as usize;
+    return "█".repeat(eighths / 8) + PARTS[eighths % 8];
+}
+
+
+/// Prints where the money goes, for drawing as a diagram: from each source to
+/// the income, from the income to each account, and from each account to each
+/// category it pays for, with anything left over going to the surplus (or a
+/// shortfall making up the difference).
+///
+/// `dot` is Graphviz's format, and `sankey` is SankeyMATIC's.
+fn print_flows(format: ReportFormat, summary: &Summary)
+{
+    let mut flows: Vec<(String, String, Money)> = Vec::new();
+
+    for (source, value) in biggest_first(&summary.income_sources, ("(other income)", summary.other_income))
+    {
+        flows.push((source.to_string(), "Income".to_string(), value));
+    }
+
+    let mut accounts: BTreeMap<&str, Money> = BTreeMap::new();
+    for ((account, _), value) in &summary.flows
+    {
+        *accounts.entry(account).or_default() += *value;
+    }
+
+    for (account, value) in accounts
+    {
+        flows.push(("Income".to_string(), account.to_string(), value));
+    }
+
+    for ((account, category), value) in &summary.flows
+    {
+        flows.push((account.clone(), category.clone(), *value));
+    }
+
+    match summary.total.mills < 0
+    {
+        true  => flows.push(("(shortfall)".to_string(), "Income".to_string(), -summary.total)),
+        false => flows.push(("Income".to_string(), "Surplus".to_string(), summary.total)),
+    }
+
+    let flows = flows.into_iter().filter(|(_, _, value)| value.mills > 0);
+
+    match format
+    {
+        ReportFormat::dot =>
+        {
+            let quote = |name: &str| format!("\"{}\"", name.replace('"', "\\\""));
+
+            println!("digraph money {{");
+            println!("    rankdir=LR;");
+            for (from, to, value) in flows
+            {
+                println!("    {} -> {} [label=\"{}\"];", quote(&from), quote(&to), value.plain());
+            }
+            println!("}}");
+        },
+
+        _ => flows.for_each(|(from, to, value)| println!("{} [{}] {}", from, value.plain(), to)),
+    }
+}
+
+
+/// the amounts by name, biggest first (whether in or out), and then `other`,
+/// for the sections of a report.
+fn biggest_first<'a>(amounts: &'a HashMap<String, Money>, other: (&'a str, Money)) -> Vec<(&'a str, Money)>
+{
+    let mut amounts: Vec<(&str, Money)> = amounts.iter().map(|(name, value)| (name.as_str(), *value)).collect();
+    amounts.sort_by(|a, b| b.1.mills.unsigned_abs().cmp(&a.1.mills.unsigned_abs()).then(a.0.cmp(b.0)));
+    amounts.push(other);
+
+    return amounts;
+}
+
+
+/// The report as GitHub-flavoured Markdown; the table of transactions, then
+/// the breakdown, income and coverage, each as a table of its own.
+fn markdown_report(title: &str, summary: &Summary) -> String
+{
+    // a `|` would end the cell early.
+    let escape = |text: &str| text.replace('|', "\\|");
+
+    let mut page = Vec::new();
+
+    page.push(format!("# {}", title));
+    page.push(String::new());
+
+    if let Some(t) = last_modified()
+    {
+        page.push(format!("Last updated: {}", format_time(t)));
+        page.push(String::new());
+    }
+
+    page.push("| Income | Expenditure | Value | Category | Account |".to_string());
+    page.push("|---|---|--:|---|---|".to_string());
+
+    for row in &summary.rows
+    {
+        let transaction = row.transaction;
+        let name = escape(&transaction.name);
+        let (income, expend) = match transaction.add_type
+        {
+            AddType::income                      => (name, String::new()),
+            AddType::expense | AddType::transfer => (String::new(), name),
+        };
+
+        page.push(format!("| {} | {} | {} | {} | {} |",
+            income,
+            expend,
+            row.amount.plain(),
+            escape(transaction.category.as_deref().unwrap_or_default()),
+            escape(&transaction.describe_accounts())));
+    }
+
+    page.push(format!("| | **Total** | **{}** | | |", summary.total.plain()));
+
+    let mut section = |heading: &str, column: &str, amounts: &HashMap<String, Money>, other: (&str, Money)|
+    {
+        page.push(String::new());
+        page.push(format!("## {}", heading));
+        page.push(String::new());
+        page.push(format!("| {} | Amount |", column));
+        page.push("|---|--:|".to_string());
+
+        for (name, value) in biggest_first(amounts, other)
+        {
+            page.push(format!("| {} | {} |", escape(name), value.plain()));
+        }
+    };
+
+    section("Breakdown", "Category", &summary.breakdown, ("(other)", summary.other_expenses));
+
+    if !summary.income_sources.is_empty()
+    {
+        section("Income by source", "Source", &summary.income_sources, ("(other)", summary.other_income));
+    }
+
+    section("Coverage", "Account", &summary.coverage, ("(unallocated)", summary.other_alloc));
+
+    return page.join("\n") + "\n";
+}
+
+
+/// The report as a complete HTML page, for emailing or viewing in a browser;
+/// the table of transactions, then the breakdown, income and coverage, each
+/// with a bar for every amount. Negative amounts are shown in red.
+fn html_report(title: &str, summary: &Summary) -> String
+{
+    let cell = |money: Money| match money.mills < 0
+    {
+        true  => format!("<td class=\"negative\">{}</td>", money.plain()),
+        false => format!("<td>{}</td>", money.plain()),
+    };
+
+    let mut page = Vec::new();
+
+    page.push("<!DOCTYPE html>".to_string());
+    page.push("<html>".to_string());
+    page.push("<head>".to_string());
+    page.push("<meta charset=\"utf-8\">".to_string());
+    page.push(format!("<title>{}</title>", escape_html(title)));
+    page.push("<style>".to_string());
+    page.push("body { font-family: sans-serif; max-width: 50em; margin: 2em auto; color: #222; }".to_string());
+    page.push("table { border-collapse: collapse; margin-bottom: 1.5em; }".to_string());
+    page.push("th, td { padding: 0.2em 0.8em; text-align: left; border-bottom: 1px solid #ddd; }".to_string());
+    page.push(".negative { color: #c00; }".to_string());
+    page.push(".bar { background: #4a7fb5; height: 0.8em; }".to_string());
+    page.push("</style>".to_string());
+    page.push("</head>".to_string());
+    page.push("<body>".to_string());
+    page.push(format!("<h1>{}</h1>", escape_html(title)));
+
+    if let Some(t) = last_modified()
+    {
+        page.push(format!("<p>Last updated: {}</p>", format_time(t)));
+    }
+
+    page.push("<table>".to_string());
+    page.push("<tr><th>Income</th><th>Expenditure</th><th>Value</th><th>Category</th><th>Account</th></tr>".to_string());
+
+    for row in &summary.rows
+    {
+        let transaction = row.transaction;
+        let name = escape_html(&transaction.name);
+        let (income, expend) = match transaction.add_type
+        {
+            AddType::income                      => (name, String::new()),
+            AddType::expense | AddType::transfer => (String::new(), name),
+        };
+
+        page.push(format!("<tr><td>{}</td><td>{}</td>{}<td>{}</td><td>{}</td></tr>",
+            income,
+            expend,
+            cell(row.amount),
+            escape_html(transaction.category.as_deref().unwrap_or_default()),
+            escape_html(&transaction.describe_accounts())));
+    }
+
+    page.push(format!("<tr><th></th><th>Total</th>{}<th></th><th></th></tr>", cell(summary.total)));
+    page.push("</table>".to_string());
+
+    // a section of amounts, biggest first, each with a bar scaled to the biggest.
+    let mut section = |heading: &str, amounts: &HashMap<String, Money>, other: (&str, Money)|
+    {
+        let amounts = biggest_first(amounts, other);
+        let biggest = amounts.iter().map(|(_, value)| value.mills.unsigned_abs()).max().unwrap_or(0).max(1);
+
+        page.push(format!("<h2>{}</h2>", heading));
+        page.push("<table>".to_string());
+
+        for (name, value) in amounts
+        {
+            let width = value.mills.unsigned_abs() as f64 / biggest as f64 * 20.0;
+            page.push(format!("<tr><td>{}</td>{}<td><div class=\"bar\" style=\"width: {:.1}em\"></div></td></tr>", escape_html(name), cell(value), width));
+        }
+
+        page.push("</table>".to_string());
+    };
+
+    section("Breakdown", &summary.breakdown, ("(other)", summary.other_expenses));
+
+    if !summary.income_sources.is_empty()
+    {
+        section("Income by source", &summary.income_sources, ("(other)", summary.other_income));
+    }
+
+    section("Coverage", &summary.coverage, ("(unallocated)", summary.other_alloc));
+
+    page.push("</body>".to_string());
+    page.push("</html>".to_string());
+
+    return page.join("\n") + "\n";
+}
+
+
+/// The report as a JSON object, as printed by `--json` and filled into templates.
+fn json_report(title: &str, summary: &Summary) -> serde_json::Value
+{
+    let amounts = |amounts: &HashMap<String, Money>, other: (&str, Money)|
+    {
+        let mut map: serde_json::Map<String, serde_json::Value> = amounts.iter()
+            .map(|(name, value)| (name.clone(), value.to_json()))
+            .collect();
+
+        map.insert(other.0.to_string(), other.1.to_json());
+        serde_json::Value::Object(map)
+    };
+
+    let rows: Vec<serde_json::Value> = summary.rows.iter()
+        .map(|row| json!({
+            "name": row.transaction.name,
+            "type": row.transaction.add_type.to_string(),
+            "amount": row.amount.to_json(),
+            "category": row.transaction.category,
+            "account": row.transaction.account,
+        }))
+        .collect();
+
+    json!({
+        "title": title,
+        "rows": rows,
+        "total": summary.total.to_json(),
+        "income": summary.income.to_json(),
+        "tax": summary.tax.to_json(),
+        "income_sources": amounts(&summary.income_sources, ("(other)", summary.other_income)),
+        "breakdown": amounts(&summary.breakdown, ("(other)", summary.other_expenses)),
+        "coverage": amounts(&summary.coverage, ("(unallocated)", summary.other_alloc)),
+    })
+}
+
+
+/// Fills in `template` from `context`, a stack of JSON values with the
+/// innermost last. This is a small part of Mustache:
+///
+/// - `{{name}}` is replaced by the value of `name`, looked for from the
+///   innermost value out; `{{a.b}}` looks inside `a`, and `{{.}}` is the
+///   innermost value itself. Amounts are written to the cent.
+/// - `{{#name}}...{{/name}}` is repeated for each item of a list, or each
+///   entry of an object (as `name` and `value`), or shown once if `name` is
+///   set to anything other than false, zero or nothing.
+/// - `{{^name}}...{{/name}}` is shown only if `name` isn't.
+fn render_template(template: &str, context: &mut Vec<serde_json::Value>) -> std::result::Result<String, String>
+{
+    let lookup = |context: &[serde_json::Value], name: &str| -> serde_json::Value
+    {
+        if name == "."
+        {
+            return context.last().cloned().unwrap_or_default();
+        }
+
+        let mut path = name.split('.');
+        let first = path.next().unwrap_or_default();
+        let found = context.iter().rev().find(|value| value.get(first).is_some()).map(|value| value[first].clone());
+
+        path.fold(found.unwrap_or_default(), |value, key| value[key].clone())
+    };
+
+    let is_set = |value: &serde_json::Value| match value
+    {
+        serde_json::Value::Null      => false,
+        serde_json::Value::Bool(b)   => *b,
+        serde_json::Value::Number(n) => n.as_f64() != Some(0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a)  => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    };
+
+    let mut output = String::new();
+    let mut rest = template;
+
+    while let Some(open) = rest.find("{{")
+    {
+        output.push_str(&rest[..open]);
+
+        let close = rest[open..].find("}}").ok_or("a {{ is never closed with }}")? + open;
+        let tag = rest[open + 2..close].trim();
+        rest = &rest[close + 2..];
+
+        let (kind, name) = match tag.chars().next()
+        {
+            Some(c @ '#') | Some(c @ '^') | Some(c @ '/') => (Some(c), tag[1..].trim()),
+            _                                             => (None, tag),
+        };
+
+        if kind == Some('/')
+        {
+            return Err(format!("{{{{/{}}}}} doesn't close anything", name));
+        }
+
+        let value = lookup(context, name);
+
+        let kind = match kind
+        {
+            Some(kind) => kind,
+            None       =>
+            {
+                match value
+                {
+                    serde_json::Value::Null                    => (),
+                    serde_json::Value::String(s)               => output.push_str(&s),
+                    serde_json::Value::Number(n) if n.is_f64() => output.push_str(&format!("{:.2}", n.as_f64().unwrap_or_default())),
+                    value                                      => output.push_str(&value.to_string()),
+                }
+                continue;
+            },
+        };
+
+        // the end of the section, skipping over any sections of the same name inside it.
+        let (open_tag, close_tag) = (format!("{{{{#{}}}}}", name), format!("{{{{/{}}}}}", name));
+        let mut depth = 0;
+        let mut end = None;
+        let mut i = 0;
+
+        while let Some(next) = rest[i..].find("{{")
+        {
+            i += next;
+            if rest[i..].starts_with(&open_tag)
+            {
+                depth += 1;
+            }
+            else if rest[i..].starts_with(&close_tag)
+            {
+                if depth == 0
+                {
+                    end = Some(i);
+                    break;
+                }
+
+                depth -= 1;
+            }
+
+            i += 2;
+        }
+
+        let end = end.ok_or_else(|| format!("{{{{{}{}}}}} is never closed with {}", kind, name, close_tag))?;
+        let inner = &rest[..end];
+        rest = &rest[end + close_tag.len()..];
+
+        let items: Vec<serde_json::Value> = match (kind, &value)
+        {
+            ('^', value) if is_set(value)           => vec![],
+            ('^', _)                                => vec![serde_json::Value::Null],
+            (_, serde_json::Value::Array(items))    => items.clone(),
+            (_, serde_json::Value::Object(entries)) => entries.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect(),
+            (_, value) if is_set(value)             => vec![value.clone()],
+            _                                       => vec![],
+        };
+
+        for item in items
+        {
+            context.push(item);
+            let rendered = render_template(inner, context);
+            context.pop();
+
+            output.push_str(&rendered?);
+        }
+    }
+
+    output.push_str(rest);
+    return Ok(output);
+}
+
+
+/// Prints the projected net for the month (or for `per`), and nothing else.
+fn total(per: Option<Period>) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let mut config = load_config()?;
+    config.apply_tax_brackets(&ledger, Local::now().date_naive());
+    let per = per.unwrap_or(Period::from(Frequency::monthly));
+    let summary = summarise_split(&ledger, per, per, None, Local::now().date_naive(), &config);
+
+    println!("{}", summary.total.signed().trim());
+
+    Ok(())
+}
+
+
+/// a field of a transaction, as stored, in words; amounts as plain money and
+/// percentages, strings without their quotes, and `(none)` if it isn't set.
+fn describe_value(value: &serde_json::Value) -> String
+{
+    match value
+    {
+        serde_json::Value::Null      => "(none)".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        _ => match (value["mills"].as_i64(), value["percent"].as_f64())
+        {
+            (Some(mills), _)   => Money { mills }.plain(),
+            (_, Some(percent)) => format!("{}%", percent),
+            _                  => value.to_string(),
+        },
+    }
+}
+
+
+/// Prints the entries that were added to, removed from, or changed between
+/// the saved ledger `a` and the saved ledger `b` (or the current ledger),
+/// matched up by id, and then how much the monthly net changed.
+fn diff(a: String, b: Option<String>) -> Result<()>
+{
+    let config = load_config()?;
+    let ledger_a = load_ledger(&a)?;
+    let ledger_b = match &b
+    {
+        Some(b) => load_ledger(b)?,
+        None    => load_current_ledger()?,
+    };
+
+    for (id, t) in sorted(&ledger_b).into_iter().filter(|(id, _)| !ledger_a.contains_key(*id))
+    {
+        println!("+ {: >4}  {} ({} {}, {})", id, t.name, t.describe_freq(), t.add_type, t.amount.plain());
+    }
+
+    for (id, t) in sorted(&ledger_a).into_iter().filter(|(id, _)| !ledger_b.contains_key(*id))
+    {
+        println!("- {: >4}  {} ({} {}, {})", id, t.name, t.describe_freq(), t.add_type, t.amount.plain());
+    }
+
+    for (id, before) in sorted(&ledger_a)
+    {
+        let after = match ledger_b.get(id)
+        {
+            Some(after) if after != before => after,
+            _                              => continue,
+        };
+
+        let (before_value, after_value) = (json!(before), json!(after));
+        let changes: Vec<String> = transaction_fields().iter()
+            .filter(|field| before_value[**field] != after_value[**field])
+            .map(|field| format!("{}: {} -> {}", field, describe_value(&before_value[*field]), describe_value(&after_value[*field])))
+            .collect();
+
+        println!("~ {: >4}  {}: {}", id, after.name, changes.join(", "));
+    }
+
+    let total_a = summarise(&ledger_a, Frequency::monthly, &config).total;
+    let total_b = summarise(&ledger_b, Frequency::monthly, &config).total;
+
+    println!();
+    println!("Monthly net: {} -> {} (a change of {})", total_a.plain(), total_b.plain(), (total_b - total_a).plain());
+
+    Ok(())
+}
+
+
+/// Prints the monthly expenses by category for the ledgers `a` and `b`
+/// side-by-side, along with the change from `a` to `b`.
+fn compare(a: String, b: String) -> Result<()>
+{
+    let config = load_config()?;
+
+    let ledger_a = load_ledger(&a)?;
+    let ledger_b = load_ledger(&b)?;
+    let summary_a = summarise(&ledger_a, Frequency::monthly, &config);
+    let summary_b = summarise(&ledger_b, Frequency::monthly, &config);
+
+    // categories that only differ in case are the same one, under whichever
+    // spelling sorts first.
+    let mut categories: Vec<&String> = summary_a.breakdown.keys()
+        .chain(summary_b.breakdown.keys())
+        .collect();
+
+    categories.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b)));
+    categories.dedup_by(|a, b| same_label(a, b));
+
+    let value = |summary: &Summary, category: &str| summary.breakdown.iter()
+        .filter(|&(c, _)| same_label(c, category))
+        .map(|(_, value)| value)
+        .sum::<Money>();
+
+    println!("{:<16}{:<12}{:<12}{:<12}", "CATEGORY", a, b, "CHANGE");
+    println!("----------------------------------------------------");
+
+    let print_row = |category: &str, value_a: Money, value_b: Money|
+    {
+        println!("{:<16}{:<12}{:<12}{:<12}", category, value_a.signed(), value_b.signed(), (value_b - value_a).signed());
+    };
+
+    for category in categories
+    {
+        print_row(category, value(&summary_a, category), value(&summary_b, category));
+    }
+
+    print_row("(other)", summary_a.other_expenses, summary_b.other_expenses);
+
+    println!("----------------------------------------------------");
+    print_row("NET:", summary_a.total, summary_b.total);
+
+    Ok(())
+}
+
+
+/// Splits the monthly income between buckets according to `rule`, and
+/// compares that with the actual expenses in each bucket.
+///
+/// Expenses are put in buckets by their category, using `category_buckets` in
+/// the config file.
+fn allocate(rule: AllocationRule) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+
+    let default_names = ["needs", "wants", "savings"];
+    let names: Vec<String> = (0..rule.percentages.len())
+        .map(|i| match config.buckets
+        {
+            Some(ref names) => names.get(i).cloned(),
+            None            => default_names.get(i).map(|n| n.to_string()),
+        }
+        .unwrap_or_else(|| format!("bucket {}", i + 1)))
+        .collect();
+
+    let mut actual: HashMap<&str, Money> = HashMap::new();
+    let mut unmapped = summary.other_expenses;
+
+    // categories and buckets are matched in any case.
+    for (category, value) in &summary.breakdown
+    {
+        let bucket = config.category_buckets.iter()
+            .find(|&(c, _)| same_label(c, category))
+            .and_then(|(_, bucket)| names.iter().find(|name| same_label(name, bucket)));
+
+        match bucket
+        {
+            Some(bucket) => *actual.entry(bucket).or_default() += *value,
+            None         => unmapped += *value,
+        }
+    }
+
+    println!("Allocation of {} monthly income:\n", summary.income.to_string().trim());
+    println!("{:<16}{:<8}{:<12}{:<12}", "BUCKET", "RULE", "TARGET", "ACTUAL");
+    println!("------------------------------------------------");
+
+    for (name, percentage) in names.iter().zip(&rule.percentages)
+    {
+        let target = summary.income.scaled(*percentage as f64 / 100.0);
+        let actual = actual.get(name.as_str()).cloned().unwrap_or_default();
+        println!("{:<16}{:<8}{:<12}{:<12}", name, format!("{}%", percentage), target.signed(), actual.signed());
+    }
+
+    println!("{:<16}{:<8}{:<12}{:<12}", "(unmapped)", "", "", unmapped.signed());
+    println!("{:<16}{:<8}{:<12}{:<12}", "(left over)", "", "", summary.total.signed());
+
+    Ok(())
+}
+
+
+/// whether `text` matches the glob `pattern`, in which `*` stands for any run
+/// of characters and `?` for any one.
+fn glob_matches(pattern: &str, text: &str) -> bool
+{
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // where the last `*` was, and where in `text` it has matched up to.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len()
+    {
+        match pattern.get(p)
+        {
+            Some('*')                            => { star = Some((p, t)); p += 1; },
+            Some(&c) if c == '?' || c == text[t] => { p += 1; t += 1; },
+            _                                    => match star
+            {
+                Some((sp, st)) => { star = Some((sp, st + 1)); p = sp + 1; t = st + 1; },
+                None           => return false,
+            },
+        }
+    }
+
+    return pattern[p..].iter().all(|&c| c == '*');
+}
+
+
+/// Searches the current ledger and every saved ledger for transactions whose
+/// name, category, accounts, payee, note or tags contain `pattern`, ignoring
+/// case. A pattern with a `*` or `?` in it is a glob instead, which the whole
+/// of one of those has to match.
+///
+/// Ledgers that can't be read are skipped with a warning.
+fn search(pattern: String) -> Result<()>
+{
+    let pattern = pattern.to_lowercase();
+    let is_glob = pattern.contains(['*', '?']);
+    let matches = |field: &str| match is_glob
+    {
+        true  => glob_matches(&pattern, &field.to_lowercase()),
+        false => field.to_lowercase().contains(&pattern),
+    };
+
+    // where a transaction matched, if not by name; the first field that does.
+    let matched_in = |t: &Transaction| -> Option<Option<(&'static str, String)>>
+    {
+        let fields = [("category", &t.category), ("account", &t.account), ("to account", &t.to_account), ("payee", &t.payee), ("note", &t.note)];
+
+        match matches(&t.name)
+        {
+            true  => Some(None),
+            false => fields.iter()
+                .filter_map(|(field, value)| value.as_ref().map(|value| (*field, value.clone())))
+                .chain(t.tags.iter().map(|tag| ("tag", tag.clone())))
+                .find(|(_, value)| matches(value))
+                .map(Some),
+        }
+    };
+
+    let ledgers = Some(".current_data".to_string()).into_iter()
+        .chain(saved_ledgers()?);
+
+    for name in ledgers
+    {
+        let ledger = match load_ledger(&name)
+        {
+            Ok(ledger) => ledger,
+            Err(_) =>
+            {
+                eprintln!("warning: skipping {}, which couldn't be read as a ledger", name);
+                continue;
+            }
+        };
+
+        let display_name = if name == ".current_data" { "(current)" } else { &name };
+        for (_, t) in sorted(&ledger)
+        {
+            match matched_in(t)
+            {
+                Some(None)                 => println!("{}: {} ({})", display_name, t.name, t.amount.to_string().trim()),
+                Some(Some((field, value))) => println!("{}: {} ({}) — {} \"{}\"", display_name, t.name, t.amount.to_string().trim(), field, value),
+                None                       => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Projects the balance forward month-by-month (or `per` by `per`), starting
+/// from this month, and reports the first month in which it goes negative.
+///
+/// Incomes grow by `raise` percent a year, and expenses by `inflation` percent,
+/// compounding monthly. Transactions are left out of the months that they skip,
+/// and prorated in those they start or end partway through. A period shorter
+/// than a month gets its share of the month it starts in.
+fn project(pc: ProjectCommand) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let mut config = load_config()?;
+
+    let today = Local::now().date_naive();
+    config.apply_tax_brackets(&ledger, today);
+    let this_month = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)
+        .expect("the first of the month is always a valid date");
+
+    let per = pc.per.unwrap_or(Period::from(Frequency::monthly));
+    if matches!(per.unit, Frequency::workdays | Frequency::once)
+    {
+        return Err(Error::CannotProjectPer(per.describe()));
+    }
+
+    let end = this_month.checked_add_months(Months::new(pc.months)).ok_or(Error::TooFarAhead)?;
+
+    // the income and expenses of the month `i` months on from this one.
+    let month_figures = |i: u32|
+    {
+        let month = this_month + Months::new(i);
+
+        let mut income = Money::default();
+        let mut expenses = Money::default();
+
+        // the part of the income that goes on percentage-of-income expenses.
+        let mut share = 0.0;
+
+        for transaction in ledger.values().filter(|t| t.active_in(month) && !t.skip_months.contains(&(month.month() as u8)))
+        {
+            // bills every few months land in full in the months they fall in, where that's known.
+            let spread = match transaction.falls_in(month)
+            {
+                Some(true)  => Ratio::whole(1),
+                Some(false) => continue,
+                None        => transaction.months_factor(&config),
+            };
+
+            let amount = match transaction.amount
+            {
+                Amount::fixed(money)     => money.times(transaction.take_home(&config) / spread * transaction.month_fraction(month)),
+                Amount::percent(percent) =>
+                {
+                    if transaction.add_type == AddType::expense
+                    {
+                        share += percent / 100.0;
+                    }
+                    continue;
+                },
+            };
+
+            match transaction.add_type
+            {
+                AddType::income   => income += amount,
+                AddType::expense  => expenses += amount,
+                AddType::transfer => (),
+            }
+        }
+
+        // percentages follow the income as it rises, rather than inflation.
+        let years = f64::from(i) / 12.0;
+        let income = income.scaled((1.0 + pc.raise / 100.0).powf(years));
+        let expenses = expenses.scaled((1.0 + pc.inflation / 100.0).powf(years)) + income.scaled(share);
+        (income, expenses)
+    };
+
+    let label = |date: NaiveDate| match per.is_months()
+    {
+        true  => date.format("%Y-%m").to_string(),
+        false => format_date(date),
+    };
+
+    println!("{:<12}{:<14}{:<14}{:<14}{:<14}", if per.is_months() { "MONTH" } else { "FROM" }, "INCOME", "EXPENSES", "NET", "BALANCE");
+    println!("------------------------------------------------------------------");
+
+    let mut balance = pc.balance;
+    let mut first_negative = None;
+
+    for (n, start) in (0..).map_while(|n| per.after(this_month, n)).take_while(|&d| d < end).enumerate()
+    {
+        let months_in = |d: NaiveDate| (d.year() - this_month.year()) as u32 * 12 + d.month() - this_month.month();
+        let month = months_in(start);
+
+        let (income, expenses) = match per.is_months()
+        {
+            true =>
             {
-                income = transaction.name.clone();
-                amount = format!(" {} ", amount);
-                total += money.cents as i64;
+                let next = per.after(start, 1).map_or(end, |d| d.min(end));
+                (month..months_in(next)).map(month_figures).fold((Money::default(), Money::default()), |(i, e), (mi, me)| (i + mi, e + me))
             },
+            false =>
+            {
+                let share = per.to_months_factor(&config);
+                let (income, expenses) = month_figures(month);
+                (income.times(share), expenses.times(share))
+            },
+        };
+
+        let net = income - expenses;
+        balance += net;
+        println!("{:<12}{:<14}{:<14}{:<14}{:<14}", label(start), income.signed(), (-expenses).signed(), net.signed(), balance.signed());
+
+        if balance.mills < 0 && first_negative.is_none()
+        {
+            first_negative = Some((n + 1, start));
+        }
+    }
+
+    println!();
+
+    match first_negative
+    {
+        Some((n, start)) => println!("Balance first goes negative in {} {} ({})", per.unit.period(), n, label(start)),
+        None             => println!("Balance stays positive for all {} months", pc.months),
+    }
+
+    Ok(())
+}
+
+
+/// Writes out the current ledger in `format`, with a header row, and each
+/// transaction's amount as entered and projected onto a month.
+fn export(format: ExportFormat) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+
+    print_rows(format, "monthly", summary.rows.iter().map(|row| (row.transaction, Some(row.amount))));
+
+    Ok(())
+}
+
+
+/// Writes out a header row, then a row for each transaction, with its amount
+/// as entered and projected (if it is); `projected` heads the projected column.
+fn print_rows<'a, I>(format: ExportFormat, projected: &str, rows: I)
+    where I: Iterator<Item = (&'a Transaction, Option<Money>)>
+{
+    let header: Vec<String> = ["name", "type", "frequency", "amount", projected, "category", "account"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    println!("{}", format.line(&header));
+
+    for (transaction, amount) in rows
+    {
+        let fields = vec![
+            transaction.name.clone(),
+            transaction.add_type.to_string(),
+            transaction.freq.to_string(),
+            transaction.amount.plain(),
+            amount.map(|a| a.plain()).unwrap_or_default(),
+            transaction.category.clone().unwrap_or_default(),
+            transaction.account.clone().unwrap_or_default(),
+        ];
+
+        println!("{}", format.line(&fields));
+    }
+}
+
+
+/// Prints a short summary of the current ledger: which saved ledger it came
+/// from, how many transactions it has, the monthly net, the biggest expense
+/// categories and when it was last changed.
+///
+/// Anything that isn't known is left out.
+fn status() -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+
+    if let Some(name) = current_name()
+    {
+        println!("{:<16}{}", "Ledger:", name);
+    }
+
+    println!("{:<16}{}", "Transactions:", ledger.len());
+    println!("{:<16}{}", "Monthly net:", summary.total.signed().trim());
+
+    let mut categories: Vec<(&String, &Money)> = summary.breakdown.iter().collect();
+    categories.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    let top: Vec<String> = categories.iter()
+        .take(3)
+        .map(|(name, value)| format!("{} {}", name, value.to_string().trim()))
+        .collect();
+
+    if !top.is_empty()
+    {
+        println!("{:<16}{}", "Top expenses:", top.join(", "));
+    }
+
+    if let Some(t) = last_modified()
+    {
+        println!("{:<16}{}", "Last updated:", format_time(t));
+    }
+
+    Ok(())
+}
+
+
+/// Prints the net worth; the balances less the debts from the config, each
+/// listed, and then what it would be after `months` more of the current
+/// monthly net.
+fn networth(months: u32) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+
+    let mut now = Money::default();
+
+    let mut section = |heading: &str, amounts: &HashMap<String, f64>, sign: f64|
+    {
+        let mut amounts: Vec<(&String, Money)> = amounts.iter().map(|(name, value)| (name, Money::from_dollars(value * sign))).collect();
+        amounts.sort();
+
+        if !amounts.is_empty()
+        {
+            println!("{}", heading);
+            for (name, value) in amounts
+            {
+                println!("  {:<20}{:<12}", name, value.signed());
+                now += value;
+            }
+            println!();
+        }
+    };
+
+    section("Accounts:", &config.balances, 1.0);
+    section("Debts:", &config.debts, -1.0);
+
+    let later = now + summary.total.scaled(f64::from(months));
+
+    println!("{:<22}{:<12}", "Net worth:", now.signed());
+    println!("{:<22}{:<12}", "Monthly net:", summary.total.signed());
+    println!("{:<22}{:<12}", format!("In {} months:", months), later.signed());
+
+    Ok(())
+}
+
+
+/// Lists the recurring expenses in any of `categories` (or tagged with one),
+/// ignoring case, with what each costs a month and a year; the costliest first.
+fn subscriptions(categories: Vec<String>) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+
+    let categories: Vec<String> = match categories.is_empty()
+    {
+        true  => vec!["subscriptions".to_string(), "subscription".to_string(), "subs".to_string()],
+        false => categories.iter().map(|c| c.trim().to_lowercase()).collect(),
+    };
+
+    let ledger: Ledger = ledger.into_iter()
+        .filter(|(_, t)| t.add_type == AddType::expense && t.freq != Frequency::once)
+        .filter(|(_, t)| t.category.iter().chain(&t.tags).any(|label| categories.contains(&label.to_lowercase())))
+        .collect();
+
+    let year = Period { count: 12, unit: Frequency::monthly };
+    let monthly = summarise(&ledger, Frequency::monthly, &config);
+    let yearly = summarise_split(&ledger, year, year, None, Local::now().date_naive(), &config);
+
+    let mut rows: Vec<(&Row, Money)> = monthly.rows.iter()
+        .zip(&yearly.rows)
+        .map(|(month, year)| (month, -year.amount))
+        .collect();
+
+    // stable, so that ties stay in order of name.
+    rows.sort_by_key(|&(_, year)| std::cmp::Reverse(year));
+
+    println!("{:<20}{:<12}{:<12}{:<10}", "SUBSCRIPTION", "MONTHLY", "YEARLY", "CATEGORY");
+    println!("------------------------------------------------------");
+
+    for (row, year) in rows
+    {
+        println!("{:<20}{:<12}{:<12}{:<10}", row.transaction.name, (-row.amount).signed(), year.signed(), row.transaction.category.clone().unwrap_or_default());
+    }
+
+    println!("------------------------------------------------------");
+    println!("{:<20}{:<12}{:<12}", "TOTAL:", (-monthly.total).signed(), (-yearly.total).signed());
+
+    Ok(())
+}
+
+
+/// Prints how many transactions there are in each group, along with their
+/// total and average amounts projected onto a month, largest total first.
+///
+/// The amounts are added up regardless of whether they are incomes or
+/// expenses. Transactions without the field being grouped by go under `(none)`,
+/// and ones with several tags are counted under each of them.
+fn stats(by: GroupBy) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let config = load_config()?;
+    let summary = summarise(&ledger, Frequency::monthly, &config);
+
+    let mut groups: HashMap<String, (usize, Money)> = HashMap::new();
+    for row in &summary.rows
+    {
+        let mut keys = by.keys(row.transaction);
+        if keys.is_empty()
+        {
+            keys.push("(none)".to_string());
+        }
+
+        for key in keys
+        {
+            let group = groups.entry(key).or_default();
+            group.0 += 1;
+            group.1 += Money { mills: row.amount.mills.saturating_abs() };
+        }
+    }
+
+    let mut groups: Vec<(String, (usize, Money))> = groups.into_iter().collect();
+    groups.sort_by(|a, b| (b.1).1.cmp(&(a.1).1).then(a.0.cmp(&b.0)));
+
+    println!("{:<20}{:<8}{:<12}{:<12}", by.to_string().to_uppercase(), "COUNT", "TOTAL", "AVERAGE");
+    println!("----------------------------------------------------");
+
+    for (name, (count, total)) in groups
+    {
+        let average = total.scaled(1.0 / count as f64);
+        println!("{:<20}{:<8}{:<12}{:<12}", name, count, total.signed(), average.signed());
+    }
+
+    Ok(())
+}
+
+
+/// Prints the transactions in the current ledger that might be duplicates:
+/// those whose names are the same once normalised, and those with the same
+/// category and amount.
+fn dupes() -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let painter = Painter::new(ColorChoice::never);
+
+    let mut by_name: BTreeMap<String, Vec<(&String, &Transaction)>> = BTreeMap::new();
+    let mut by_category: BTreeMap<(&str, Money), Vec<(&String, &Transaction)>> = BTreeMap::new();
+
+    for (id, t) in sorted(&ledger)
+    {
+        by_name.entry(normalise_name(&t.name)).or_default().push((id, t));
+
+        if let (Some(ref category), Some(amount)) = (&t.category, t.amount.money())
+        {
+            by_category.entry((category, amount)).or_default().push((id, t));
+        }
+    }
+
+    let groups = by_name.iter()
+        .map(|(name, group)| (format!("same name ({}):", name), group))
+        .chain(by_category.iter()
+            .map(|((category, amount), group)| (format!("same category and amount ({}, {}):", category, amount.to_string().trim()), group)))
+        .filter(|(_, group)| group.len() > 1);
+
+    let mut found = false;
+    for (heading, group) in groups
+    {
+        if found
+        {
+            println!();
+        }
+
+        println!("{}", heading);
+        let width = name_width(group.iter().map(|&(_, t)| t));
+        group.iter().for_each(|&(id, t)| print_transaction(&painter, id, t, width));
+        found = true;
+    }
+
+    if !found
+    {
+        println!("no duplicates found");
+    }
+
+    Ok(())
+}
+
+
+/// the name of the saved ledger that the current ledger was last loaded from
+/// or saved as, if there is one.
+fn current_name() -> Option<String>
+{
+    let name = fs::read_to_string(get_path(".current_name").ok()?).ok()?;
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+
+/// notes `name` as the saved ledger that the current ledger corresponds to.
+/// pfr's own files, such as the backup, aren't noted.
+fn set_current_name(name: &str) -> Result<()>
+{
+    if name.starts_with('.')
+    {
+        return Ok(());
+    }
+
+    fs::write(get_path(".current_name")?, name)
+        .map_err(Error::WhileAttemptingToOpenDataFile)
+}
+
+
+/// Prints a report for the current ledger with the changes in `wc` applied,
+/// followed by how much they change the net by. The ledger itself is left
+/// alone.
+fn whatif(wc: WhatIfCommand) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let mut changed = load_current_ledger()?;
+
+    for o in wc.set
+    {
+        let id = find(&changed, &o.name)?;
+        if let Some(transaction) = changed.get_mut(&id)
+        {
+            transaction.amount = Amount::fixed(o.amount);
+        }
+    }
+
+    for name in wc.remove
+    {
+        let id = find(&changed, &name)?;
+        changed.remove(&id);
+    }
+
+    let WhatIfThen::report(rc) = wc.then;
+    let mut config = rc.load_config()?;
+    let (income_per, expense_per) = rc.pers(&config)?;
+
+    config.apply_tax_brackets(&ledger, rc.date());
+    let before = summarise_split(&ledger, income_per, expense_per, rc.round_to, rc.date(), &config).total;
+    config.apply_tax_brackets(&changed, rc.date());
+    let after = summarise_split(&changed, income_per, expense_per, rc.round_to, rc.date(), &config).total;
+
+    // with `--json`, the change goes in the report's object rather than after it.
+    let mut extra = serde_json::Map::new();
+    extra.insert("whatif".to_string(), json!({ "net": after.to_json(), "currently": before.to_json(), "change": (after - before).to_json() }));
+    report_on(&changed, &rc, extra)?;
+
+    if !json_output()
+    {
+        println!();
+        println!("What if: net {} (currently {}, a change of {})", after.plain(), before.plain(), (after - before).plain());
+    }
+
+    Ok(())
+}
+
+
+/// A line from a bank statement.
+struct StatementLine
+{
+    date: NaiveDate,
+    description: String,
+    amount: Money,
+}
+
+
+/// reads a bank statement in CSV format.
+///
+/// The first line must be a header naming the `date`, `description` (or
+/// `name`, `payee` or `memo`) and `amount` columns; any others are ignored.
+/// Dates are YYYY-MM-DD or DD/MM/YYYY, and money going out is negative.
+fn read_statement(path: &PathBuf) -> Result<Vec<StatementLine>>
+{
+    let text = fs::read_to_string(path).map_err(Error::WhileReadingStatement)?;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+    let header: Vec<String> = ExportFormat::csv.split(lines.next().unwrap_or_default())
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+
+    let column = |names: &[&str]|
+    {
+        header.iter().position(|h| names.contains(&h.as_str()))
+            .ok_or_else(|| Error::InvalidStatement(format!("there is no {} column", names[0])))
+    };
+
+    let date_col = column(&["date"])?;
+    let description_col = column(&["description", "name", "payee", "memo"])?;
+    let amount_col = column(&["amount"])?;
+
+    let mut statement = Vec::new();
+    for (i, line) in lines.enumerate()
+    {
+        let fields = ExportFormat::csv.split(line);
+        let field = |col: usize| fields.get(col).map(|f| f.trim()).unwrap_or_default();
+        let invalid = |col: usize, what: &str| Error::InvalidStatement(format!("line {}: {:?} is not a valid {}", i + 2, field(col), what));
+
+        let date = NaiveDate::parse_from_str(field(date_col), "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(field(date_col), "%d/%m/%Y"))
+            .map_err(|_| invalid(date_col, "date"))?;
+
+        let amount = Money::from_str(&field(amount_col).replace(['$', ','], ""))
+            .map_err(|_| invalid(amount_col, "amount"))?;
+
+        statement.push(StatementLine { date, description: field(description_col).to_string(), amount });
+    }
+
+    return Ok(statement);
+}
+
+
+/// true if `line` looks like it is an occurrence of `transaction`: its
+/// description contains the transaction's name, and the amounts are within
+/// `tolerance`.
+fn is_match(line: &StatementLine, transaction: &Transaction, tolerance: Money) -> bool
+{
+    // a percentage of income can't be matched to a line on its own.
+    let amount = match transaction.amount.money()
+    {
+        Some(amount) => amount,
+        None         => return false,
+    };
+
+    let expected = match transaction.add_type
+    {
+        AddType::income                      => amount,
+        AddType::expense | AddType::transfer => -amount,
+    };
+
+    let difference = line.amount - expected;
+
+    line.description.to_lowercase().contains(&transaction.name.to_lowercase())
+        && difference <= tolerance
+        && -difference <= tolerance
+}
+
+
+/// Checks the current ledger against a bank statement for a month, listing
+/// the transactions that should have happened in that month but aren't in the
+/// statement, and the statement lines that don't match any transaction.
+///
+/// Quarterly and yearly transactions aren't expected in any particular month,
+/// but statement lines that match them aren't reported as unexpected.
+fn audit(ac: AuditCommand) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let statement = read_statement(&ac.statement)?;
+
+    let month = match ac.month
+    {
+        Some(month) => month,
+        None        => parse_year_month(&Local::now().format("%Y-%m").to_string()).expect("this month is a valid month"),
+    };
+
+    let lines: Vec<&StatementLine> = statement.iter()
+        .filter(|line| line.date.year() == month.year() && line.date.month() == month.month())
+        .collect();
+
+    let transactions = sorted(&ledger);
+
+    let missing: Vec<&(&String, &Transaction)> = transactions.iter()
+        .filter(|(_, t)| !matches!(t.freq, Frequency::quarterly | Frequency::yearly) && t.every.is_none_or(|n| n == 1))
+        .filter(|(_, t)| t.active_in(month) && !t.skip_months.contains(&(month.month() as u8)))
+        .filter(|(_, t)| !lines.iter().any(|line| is_match(line, t, ac.tolerance)))
+        .collect();
+
+    let unexpected: Vec<&&StatementLine> = lines.iter()
+        .filter(|line| !transactions.iter().any(|(_, t)| is_match(line, t, ac.tolerance)))
+        .collect();
+
+    println!("Audit for {} ({} statement lines)\n", month.format("%Y-%m"), lines.len());
+
+    println!("Expected, but not in the statement:");
+    let painter = Painter::new(ColorChoice::auto);
+    let width = name_width(missing.iter().map(|&&(_, t)| t));
+    missing.iter().for_each(|&&(id, t)| print_transaction(&painter, id, t, width));
+    if missing.is_empty()
+    {
+        println!("(none)");
+    }
+
+    println!("\nIn the statement, but not expected:");
+    for line in &unexpected
+    {
+        println!("{}\t{: <30}\t{}", format_date(line.date), line.description, painter.paint(&line.amount.to_string(), line.amount));
+    }
+    if unexpected.is_empty()
+    {
+        println!("(none)");
+    }
+
+    Ok(())
+}
+
+
+/// Lists the monthly expenses with a due day that fall due in the next
+/// `within` days (counting today), soonest first.
+fn upcoming(within: i64) -> Result<()>
+{
+    let ledger = load_current_ledger()?;
+    let today = Local::now().date_naive();
+    let until = chrono::Duration::try_days(within).and_then(|days| today.checked_add_signed(days)).ok_or(Error::TooFarAhead)?;
+
+    let mut due: Vec<(NaiveDate, &Transaction)> = ledger.values()
+        .filter(|t| t.add_type == AddType::expense)
+        .filter_map(|t| t.next_due(today).map(|date| (date, t)))
+        .filter(|&(date, _)| date <= until)
+        .collect();
+
+    due.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.name.cmp(&b.1.name)));
+
+    let config = load_config()?;
+    let income = monthly_income(&ledger, today, &config);
+
+    let painter = Painter::new(ColorChoice::auto);
+    for (date, t) in &due
+    {
+        let amount = -t.money(income, &config);
+        println!("{}\t{: <20}\t{}", format_date(*date), t.name, painter.paint(&amount.to_string(), amount));
+    }
+
+    if due.is_empty()
+    {
+        println!("nothing due in the next {} days", within);
+    }
+
+    Ok(())
+}
+
+
+/// Lists the changes made to the current ledger (the most recent `limit` of
+/// them, if given), or shows the ledger as it was after entry number `at`.
+fn history(limit: Option<usize>, at: Option<usize>) -> Result<()>
+{
+    let entries = load_history()?;
+
+    if let Some(n) = at
+    {
+        if n == 0 || n > entries.len()
+        {
+            return Err(Error::NoSuchHistoryEntry(n));
+        }
+
+        let ledger = replay(&entries[..n]);
+        let painter = Painter::new(ColorChoice::auto);
+
+        let width = name_width(ledger.values());
+        sorted(&ledger).into_iter().for_each(|(id, t)| print_transaction(&painter, id, t, width));
+
+        return Ok(());
+    }
+
+    let start = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+
+    // replayed as we go, to find the names of the removed transactions.
+    let mut ledger = replay(&entries[..start]);
+
+    for (i, entry) in entries.iter().enumerate().skip(start)
+    {
+        let time = DateTime::parse_from_rfc3339(&entry.time)
+            .map(|t| format_time(t.with_timezone(&Local)))
+            .unwrap_or_else(|_| entry.time.clone());
+
+        let names: Vec<String> = entry.changed.iter().map(|c| c.transaction.name.clone())
+            .chain(entry.removed.iter().map(|key| format!("{} (removed)", ledger.get(key).map_or(key, |t| &t.name))))
+            .collect();
 
-            AddType::expense =>
+        println!("{:>4}  {}  {}: {}", i + 1, time, entry.command, names.join(", "));
+
+        entry.apply_to(&mut ledger);
+    }
+
+    Ok(())
+}
+
+
+/// Puts the current ledger back as it was before the last change that hasn't
+/// already been undone, replaying the history up to it.
+///
+/// Entries that pfr added itself, such as the ledger as it was before the
+/// history was kept, can't be undone. Transactions that come back, such as
+/// after an `rm`, are taken out of the trash.
+fn undo() -> Result<()>
+{
+    let entries = load_history()?;
+
+    // the numbers of the entries that are still in effect, oldest first.
+    let mut done: Vec<usize> = Vec::new();
+    for (i, entry) in entries.iter().enumerate()
+    {
+        match entry.undoes
+        {
+            Some(n)                                => done.retain(|&d| d != n),
+            None if entry.command.starts_with('(') => (),
+            None                                   => done.push(i + 1),
+        }
+    }
+
+    let n = done.pop().ok_or(Error::NothingToUndo)?;
+    let entry = &entries[n - 1];
+
+    let current = load_current_ledger()?;
+    let ledger = replay(&entries[..n - 1]);
+
+    // entries that come back leave the trash, and those that a restore took
+    // out of it go back in.
+    let before = load_trash()?;
+    let mut trash = before.clone();
+    trash.retain(|id, t| current.contains_key(id) || ledger.get(id) != Some(t));
+    trash.extend(entry.restored.iter().map(|change| (change.key().to_string(), change.transaction.clone())));
+
+    save_current_ledger_noting(ledger, Some(n), Vec::new())?;
+    if trash != before
+    {
+        save_ledger(".trash", trash)?;
+    }
+
+    println!("Undid {} ({}, entry {} in `pfr history`)", entry.command, DateTime::parse_from_rfc3339(&entry.time)
+        .map(|t| format_time(t.with_timezone(&Local)))
+        .unwrap_or_else(|_| entry.time.clone()), n);
+
+    Ok(())
+}
+
+
+/// Checks that the data directory can be written to, and that the current
+/// ledger and each of pfr's other files can be read, printing a line for each.
+/// Errors if any of them fail.
+fn doctor() -> Result<()>
+{
+    let dir = data_dir()?;
+    let mut failed = 0;
+
+    let mut check = |what: String, outcome: std::result::Result<String, String>|
+    {
+        match outcome
+        {
+            Ok(detail) => println!("ok    {}{}", what, detail),
+            Err(e)     =>
             {
-                expend = transaction.name.clone();
-                amount = format!("({})", amount);
-                total -= money.cents as i64;
+                println!("FAIL  {}: {}", what, e);
+                failed += 1;
+            },
+        }
+    };
 
-                match transaction.category
-                {
-                    Some(ref s) =>
-                    {
-                        let entry = breakdown.entry(s.clone()).or_insert(0);
-                        *entry += money.cents;
-                    },
+    let probe = dir.join(".doctor");
+    check(format!("data directory {} is writable", dir.display()), fs::write(&probe, "")
+        .and_then(|_| fs::remove_file(&probe))
+        .map(|_| String::new())
+        .map_err(|e| e.to_string()));
 
-                    None => other_expenses += money.cents,
-                }
+    check("current ledger loads".to_string(), load_current_ledger()
+        .map(|ledger| format!(" ({} transactions)", ledger.len()))
+        .map_err(|_| "it couldn't be read; run `pfr init` if you haven't yet, or try `pfr restore`".to_string()));
 
-                match transaction.account
-                {
-                    Some(ref s) =>
-                    {
-                        let entry = coverage.entry(s.clone()).or_insert(0);
-                        *entry += money.cents;
-                    },
+    check("config loads".to_string(), load_config()
+        .map(|_| String::new())
+        .map_err(|e| match e
+        {
+            Error::WhileReadingConfig(e) => e.to_string(),
+            _                            => "it couldn't be opened".to_string(),
+        }));
 
-                    None => other_alloc += money.cents,
-                }
-            }
+    let sidecars = [(".trash", "trash"), (".current_backup", "backup")];
+    for &(file, what) in &sidecars
+    {
+        if dir.join(file).exists()
+        {
+            check(format!("{} loads", what), load_ledger(file)
+                .map(|ledger| format!(" ({} transactions)", ledger.len()))
+                .map_err(|_| "it couldn't be read as a ledger".to_string()));
         }
+    }
 
-        println!("{:<20}{:<20}{:<12}{:<10}{:<8}", income, expend, amount, cat, accnt);
+    if dir.join(".history").exists()
+    {
+        check("history loads".to_string(), load_history()
+            .map(|entries| format!(" ({} entries)", entries.len()))
+            .map_err(|_| "a line couldn't be read".to_string()));
     }
 
-    println!("-----------------------------------------------------------------------");
+    for name in saved_ledgers().unwrap_or_default()
+    {
+        check(format!("saved ledger {} loads", name), load_ledger(&name)
+            .map(|ledger| format!(" ({} transactions)", ledger.len()))
+            .map_err(|_| "it couldn't be read as a ledger".to_string()));
+    }
 
-    let total_str = if total > 0
+    match failed
     {
-        format!(" {} ", Money { cents: total as u64 }.to_string())
+        0 => Ok(()),
+        n => Err(Error::ChecksFailed(n)),
     }
-    else
+}
+
+
+/// A transaction with every field filled in, for `schema`.
+fn example_transaction() -> Transaction
+{
+    Transaction
     {
-        let total = -total;
-        format!("({})", Money { cents: total as u64 }.to_string())
-    };
+        add_type: AddType::expense,
+        freq: Frequency::monthly,
+        every: Some(1),
+        name: "rent".to_string(),
+        amount: Amount::fixed(Money::from_dollars(1200.0)),
+        category: Some("housing".to_string()),
+        splits: vec![Split { category: "utilities".to_string(), percent: 10.0 }],
+        account: Some("everyday".to_string()),
+        to_account: Some("savings".to_string()),
+        payee: Some("the landlord".to_string()),
+        skip_months: vec![12],
+        share: Some(50.0),
+        discretionary: true,
+        gross: true,
+        estimated: true,
+        min: Some(Money::from_dollars(1150.0)),
+        max: Some(Money::from_dollars(1300.0)),
+        due_day: Some(1),
+        start_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+        end_date: NaiveDate::from_ymd_opt(2025, 2, 28),
+        tags: vec!["household".to_string()],
+        note: Some("goes up in March".to_string()),
+        attachments: vec!["attachments/1-lease.pdf".to_string()],
+        paused: true,
+    }
+}
+
 
-    println!("{:<20}{:<20}{:<12}{:<10}{:<8}\n", "", "TOTAL: ", total_str, "", "");
+/// the JSON Schema for values shaped like `value`.
+fn schema_of(value: &serde_json::Value) -> serde_json::Value
+{
+    use serde_json::Value;
 
-    println!("Breakdown:");
-    for (name, value) in &breakdown
+    match *value
     {
-        println!("{:<16}{:10}", name, Money{ cents: *value });
+        Value::Null          => json!({ "type": "null" }),
+        Value::Bool(_)       => json!({ "type": "boolean" }),
+        Value::Number(ref n) => json!({ "type": if n.is_f64() { "number" } else { "integer" } }),
+        Value::String(_)     => json!({ "type": "string" }),
+        Value::Array(ref a)  => json!({ "type": "array", "items": a.first().map_or(json!({}), schema_of) }),
+        Value::Object(ref o) => json!({
+            "type": "object",
+            "properties": o.iter().map(|(k, v)| (k.clone(), schema_of(v))).collect::<serde_json::Map<String, Value>>(),
+        }),
     }
+}
 
-    println!("{:<16}{:<10}\n", "(other)", Money{ cents: other_expenses });
 
-    println!("Coverage:");
-    for (name, value) in &coverage
+/// Prints a JSON Schema for the ledger file, or an example transaction.
+///
+/// Both are worked out from the `Transaction` type itself; the fields and
+/// their types from how the example is serialised, and which fields are
+/// required (or can be null) by leaving each one out (or setting it to null)
+/// in turn, and seeing if it still loads.
+fn schema(example: bool) -> Result<()>
+{
+    let value = serde_json::to_value(example_transaction()).map_err(Error::DuringSerialisation)?;
+
+    if example
     {
-        println!("{:<10} -> {:<10}", Money{ cents: *value }, name);
+        println!("{}", serde_json::to_string_pretty(&value).map_err(Error::DuringSerialisation)?);
+        return Ok(());
     }
 
-    // calculate total coverage
-    let coverage_total = coverage.iter().map(|(_, val)| val).sum();
+    let fields = value.as_object().cloned().unwrap_or_default();
+
+    let loads = |fields: serde_json::Map<String, serde_json::Value>| serde_json::from_value::<Transaction>(serde_json::Value::Object(fields)).is_ok();
+
+    let mut transaction = schema_of(&value);
+    let mut required = Vec::new();
+
+    for field in fields.keys()
+    {
+        let mut without = fields.clone();
+        without.remove(field);
+        if !loads(without)
+        {
+            required.push(field.clone());
+        }
+
+        let mut nulled = fields.clone();
+        nulled.insert(field.clone(), serde_json::Value::Null);
+        if loads(nulled)
+        {
+            let kind = transaction["properties"][field]["type"].clone();
+            transaction["properties"][field]["type"] = json!([kind, "null"]);
+        }
+    }
+
+    transaction["required"] = json!(required);
+    transaction["additionalProperties"] = json!(false);
+    transaction["properties"]["add_type"]["enum"] = json!(AddType::variants());
+    transaction["properties"]["freq"]["enum"] = json!(Frequency::variants());
+    transaction["properties"]["skip_months"]["items"]["minimum"] = json!(1);
+    transaction["properties"]["skip_months"]["items"]["maximum"] = json!(12);
+    transaction["properties"]["due_day"]["minimum"] = json!(1);
+    transaction["properties"]["due_day"]["maximum"] = json!(31);
+
+    let ledger = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "pfr ledger",
+        "type": "object",
+        "required": ["version", "transactions"],
+        "properties": {
+            "version": { "const": LEDGER_VERSION },
+            "transactions": {
+                "description": "transactions, keyed by id",
+                "type": "object",
+                "additionalProperties": transaction,
+            },
+        },
+    });
+
+    println!("{}", serde_json::to_string_pretty(&ledger).map_err(Error::DuringSerialisation)?);
+
+    Ok(())
+}
+
+
+/// prints the path of the ledger called `name`, or of the current ledger.
+fn path(name: Option<String>) -> Result<()>
+{
+    let name = name.unwrap_or_else(|| ".current_data".to_string());
+    println!("{}", get_path(&name)?.display());
+
+    Ok(())
+}
+
+
+/// the commands that take the name of an entry, and of a saved ledger.
+const ENTRY_COMMANDS: &str = "rm edit show pause resume attach open";
+const LEDGER_COMMANDS: &str = "load compare diff";
+
+
+/// prints the completion script for `shell`; for all but powershell, along
+/// with a bit more that completes entry and ledger names, from `complete-names`.
+fn completions(shell: Shell) -> Result<()>
+{
+    let mut script = Vec::new();
+    Opt::clap().gen_completions_to("pfr", shell, &mut script);
+
+    // zsh runs the script as the `pfr` completion function, which ends by
+    // calling `_pfr`; that is left for `_pfr_names` to do.
+    let script = String::from_utf8_lossy(&script);
+    match shell
+    {
+        Shell::Zsh => println!("{}", script.trim_end().trim_end_matches("_pfr \"$@\"")),
+        _          => print!("{}", script),
+    }
+
+    let (entries, ledgers) = (ENTRY_COMMANDS.replace(' ', "|"), LEDGER_COMMANDS.replace(' ', "|"));
+    match shell
+    {
+        Shell::Bash =>
+        {
+            println!("_pfr_names()\n{{");
+            println!("    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" kind=");
+            println!("    case \"${{COMP_WORDS[1]}}\" in {}) kind=entries ;; {}) kind=ledgers ;; esac", entries, ledgers);
+            println!("    if [[ -n $kind && $COMP_CWORD -ge 2 && $cur != -* ]]; then");
+            println!("        mapfile -t COMPREPLY < <(IFS=$'\\n' compgen -W \"$(pfr complete-names $kind 2>/dev/null)\" -- \"$cur\" | while read -r name; do printf '%q\\n' \"$name\"; done)");
+            println!("        [[ ${{#COMPREPLY[@]}} -gt 0 ]] && return");
+            println!("    fi");
+            println!("    _pfr \"$@\"");
+            println!("}}\n");
+            println!("complete -F _pfr_names -o bashdefault -o default pfr");
+        },
+
+        Shell::Zsh =>
+        {
+            println!("_pfr_names()\n{{");
+            println!("    local kind");
+            println!("    case $words[2] in {}) kind=entries ;; {}) kind=ledgers ;; esac", entries, ledgers);
+            println!("    if [[ -n $kind ]] && (( CURRENT > 2 )) && [[ $words[CURRENT] != -* ]]; then");
+            println!("        compadd -- ${{(f)\"$(pfr complete-names $kind 2>/dev/null)\"}} && return");
+            println!("    fi");
+            println!("    _pfr \"$@\"");
+            println!("}}\n");
+            println!("_pfr_names \"$@\"");
+        },
+
+        Shell::Fish =>
+        {
+            println!("complete -c pfr -n \"__fish_seen_subcommand_from {}\" -f -a \"(pfr complete-names entries 2>/dev/null)\"", ENTRY_COMMANDS);
+            println!("complete -c pfr -n \"__fish_seen_subcommand_from {}\" -f -a \"(pfr complete-names ledgers 2>/dev/null)\"", LEDGER_COMMANDS);
+        },
+
+        Shell::PowerShell => (),
+    }
+
+    Ok(())
+}
 
-    println!("{:<10}    {:<10}", Money{ cents: other_alloc },    "(unallocated)");
-    println!("{:<10}    {:<10}", Money{ cents: coverage_total }, "(total)");
+
+/// prints the names of the entries in the current ledger, or of the saved
+/// ledgers, one per line.
+fn complete_names(kind: NameKind) -> Result<()>
+{
+    let mut names = match kind
+    {
+        NameKind::entries => load_current_ledger()?.into_values().map(|t| t.name).collect(),
+        NameKind::ledgers => saved_ledgers()?,
+    };
+
+    names.sort();
+    names.dedup();
+    names.iter().for_each(|name| println!("{}", name));
 
     Ok(())
 }
 
 
 /// changes the current ledger to be the one called `name`
-fn load(name: String) -> Result<()>
+///
+/// If `backup_before` is set, the current ledger is backed up first (unless
+/// it is empty), so that `restore` can undo this.
+fn load(name: String, backup_before: bool) -> Result<()>
 {
-    save_ledger(".current_data", load_ledger(&name)?)
+    // read `name` before backing up, in case it is the backup.
+    let ledger = load_ledger(&name)?;
+
+    if backup_before
+    {
+        let current = load_current_ledger().unwrap_or_default();
+        if !current.is_empty()
+        {
+            save_ledger(".current_backup", current)?;
+        }
+    }
+
+    save_current_ledger(ledger)?;
+    set_current_name(&name)
 }
 
 
 /// saves the current ledger to file as `name`
 fn save(name: String) -> Result<()>
 {
-    save_ledger(&name, load_current_ledger()?)
+    save_ledger(&name, load_current_ledger()?)?;
+    set_current_name(&name)
 }
 
 
@@ -472,8 +6494,433 @@ fn backup() -> Result<()>
 
 
 /// restores the backup
-fn restore() -> Result<()>
+fn restore(backup_before: bool) -> Result<()>
 {
-    load(".current_backup".to_string())
+    load(".current_backup".to_string(), backup_before)
 }
 
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn args(line: &str) -> Vec<String>
+    {
+        line.split(' ').map(String::from).collect()
+    }
+
+    /// a transaction, as `pfr add <line>` would make it.
+    fn transaction(line: &str) -> Transaction
+    {
+        Transaction::from_clap(&Transaction::clap().get_matches_from(args(&format!("add {}", line))))
+    }
+
+    /// the tests that use the data directory take turns with it, starting
+    /// each with it empty.
+    fn data_dir_lock() -> std::sync::MutexGuard<'static, ()>
+    {
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let dir = DATA_DIR.get_or_init(|| env::temp_dir().join(format!("pfr-test-{}", std::process::id())));
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        return guard;
+    }
+
+    #[test]
+    fn weekly_amounts_are_a_year_of_weeks_over_twelve()
+    {
+        let config = Config::default();
+        let monthly = Frequency::monthly.to_months_factor(&config) / Frequency::weekly.to_months_factor(&config);
+        assert_eq!(monthly, Ratio::new(365, 84));
+
+        // 60 * 365 / 84 = 260.714..., rounded once.
+        assert_eq!(Money::from_dollars(60.0).times(monthly), Money { mills: 260_714 });
+        assert_eq!(Money::from_dollars(-60.0).times(monthly), Money { mills: -260_714 });
+    }
+
+    #[test]
+    fn each_period_has_its_share_of_a_month()
+    {
+        let config = Config::default();
+        let months = |s: &str| Period::from_str(s).unwrap().to_months_factor(&config);
+
+        assert_eq!(months("1d"), Ratio::new(12, 365));
+        assert_eq!(months("daily"), Ratio::new(12, 365));
+        assert_eq!(months("workdays"), Ratio::new(84, 365 * 5));
+        assert_eq!(months("1w"), Ratio::new(84, 365));
+        assert_eq!(months("weekly"), Ratio::new(84, 365));
+        assert_eq!(months("1f"), Ratio::new(168, 365));
+        assert_eq!(months("fortnightly"), Ratio::new(168, 365));
+        assert_eq!(months("1m"), Ratio::whole(1));
+        assert_eq!(months("monthly"), Ratio::whole(1));
+        assert_eq!(months("q"), Ratio::whole(3));
+        assert_eq!(months("quarterly"), Ratio::whole(3));
+        assert_eq!(months("1y"), Ratio::whole(12));
+        assert_eq!(months("yearly"), Ratio::whole(12));
+        assert_eq!(months("once"), Ratio::whole(1));
+        assert_eq!(months("3m"), Ratio::whole(3));
+        assert_eq!(months("2w"), Ratio::new(168, 365));
+        assert_eq!(months("2y"), Ratio::whole(24));
+    }
+
+    #[test]
+    fn periods_follow_the_config()
+    {
+        let config = Config { days_per_month: Some(30.0), weeks_per_month: Some(4.0), ..Config::default() };
+        let months = |s: &str| Period::from_str(s).unwrap().to_months_factor(&config);
+
+        assert_eq!(months("1d"), Ratio::new(1, 30));
+        assert_eq!(months("1w"), Ratio::new(1, 4));
+        assert_eq!(months("workdays"), Ratio::new(1, 20));
+        assert_eq!(months("1f"), Ratio::new(1, 2));
+    }
+
+    #[test]
+    fn times_rounds_halves_away_from_zero_and_saturates()
+    {
+        assert_eq!(Money { mills: 5 }.times(Ratio::new(1, 2)), Money { mills: 3 });
+        assert_eq!(Money { mills: -5 }.times(Ratio::new(1, 2)), Money { mills: -3 });
+        assert_eq!(Money { mills: i64::MAX }.times(Ratio::whole(12)), Money { mills: i64::MAX });
+        assert_eq!(Money { mills: i64::MIN }.times(Ratio::whole(12)), Money { mills: i64::MIN });
+    }
+
+    #[test]
+    fn categories_that_only_differ_in_case_share_a_breakdown()
+    {
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("expense monthly lunch 20 --category Food"));
+        insert(&mut ledger, transaction("expense monthly dinner 30 --category food"));
+        insert(&mut ledger, transaction("expense monthly shop 50 --category home --split FOOD:50"));
+
+        let summary = summarise(&ledger, Frequency::monthly, &Config::default());
+        assert_eq!(summary.breakdown.len(), 2);
+        assert_eq!(summary.breakdown.get("FOOD"), Some(&Money::from_dollars(75.0)));
+        assert_eq!(summary.breakdown.get("home"), Some(&Money::from_dollars(25.0)));
+    }
+
+    #[test]
+    fn rm_filters_ignore_case()
+    {
+        let rm = RmCommand::from_clap(&RmCommand::clap().get_matches_from(args("rm --category Subscriptions --account joint")));
+
+        assert!(rm.matches(&transaction("expense monthly netflix 15 --category subscriptions --account Joint")));
+        assert!(!rm.matches(&transaction("expense monthly netflix 15 --category subscriptions")));
+        assert!(!rm.matches(&transaction("expense monthly rent 1500 --category housing --account joint")));
+    }
+
+    #[test]
+    fn a_large_negative_net_adds_up_and_prints_in_parentheses()
+    {
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("income monthly work 5000"));
+        insert(&mut ledger, transaction("expense monthly mortgage 4000000000"));
+        insert(&mut ledger, transaction("expense monthly yacht 2500000000.50"));
+
+        let total = summarise(&ledger, Frequency::monthly, &Config::default()).total;
+        assert_eq!(total, Money { mills: -6_499_995_000_500 });
+        assert_eq!(total.signed(), "(6499995000.50)");
+        assert_eq!(total.plain(), "-6499995000.50");
+    }
+
+    #[test]
+    fn a_net_too_large_to_hold_stops_at_the_smallest_amount()
+    {
+        let mut huge = transaction("expense monthly huge 1");
+        huge.amount = Amount::fixed(Money { mills: i64::MAX });
+
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, huge.clone());
+        insert(&mut ledger, Transaction { name: "huger".to_string(), ..huge });
+
+        let total = summarise(&ledger, Frequency::monthly, &Config::default()).total;
+        assert_eq!(total, Money { mills: i64::MIN });
+        assert_eq!(total.signed(), "(9223372036854775.81)");
+    }
+
+    #[test]
+    fn round_to_rounds_halves_away_from_zero()
+    {
+        let dollars = |d: f64| Money::from_dollars(d);
+        let round = |amount: f64, cents: i64| dollars(amount).round_to(Money { mills: cents * 10 });
+
+        // to the nearest 100 cents.
+        assert_eq!(round(1.49, 100), dollars(1.0));
+        assert_eq!(round(1.50, 100), dollars(2.0));
+        assert_eq!(round(-1.49, 100), dollars(-1.0));
+        assert_eq!(round(-1.50, 100), dollars(-2.0));
+
+        // to the nearest 500 cents.
+        assert_eq!(round(47.0, 500), dollars(45.0));
+        assert_eq!(round(47.49, 500), dollars(45.0));
+        assert_eq!(round(47.50, 500), dollars(50.0));
+        assert_eq!(round(-47.0, 500), dollars(-45.0));
+        assert_eq!(round(-47.49, 500), dollars(-45.0));
+        assert_eq!(round(-47.50, 500), dollars(-50.0));
+
+        // to the nearest 1000 cents.
+        assert_eq!(round(14.99, 1000), dollars(10.0));
+        assert_eq!(round(15.0, 1000), dollars(20.0));
+        assert_eq!(round(-14.99, 1000), dollars(-10.0));
+        assert_eq!(round(-15.0, 1000), dollars(-20.0));
+        assert_eq!(round(0.0, 1000), dollars(0.0));
+
+        // a negative increment is the same as a positive one, and 0 leaves the amount alone.
+        assert_eq!(round(-15.0, -1000), dollars(-20.0));
+        assert_eq!(round(47.0, 0), dollars(47.0));
+    }
+
+    #[test]
+    fn estimated_is_only_saved_when_it_is_set()
+    {
+        let fixed = serde_json::to_value(transaction("expense monthly rent 1200")).unwrap();
+        assert!(fixed.get("estimated").is_none());
+
+        let estimated = serde_json::to_value(transaction("expense monthly power 160 --estimated")).unwrap();
+        assert_eq!(estimated["estimated"], json!(true));
+        assert!(serde_json::from_value::<Transaction>(fixed).is_ok_and(|t| !t.estimated));
+    }
+
+    #[test]
+    fn a_one_off_counts_once_in_the_period_it_happens_in()
+    {
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("expense once laptop 1200 --start-date 2025-06-01"));
+
+        let config = Config::default();
+        let on = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let total = |per: &str|
+        {
+            let per = Period::from_str(per).unwrap();
+            summarise_split(&ledger, per, per, None, on, &config).total
+        };
+
+        assert_eq!(total("yearly"), Money::from_dollars(-1200.0));
+        assert_eq!(total("4m"), Money::from_dollars(-1200.0));
+        assert_eq!(total("3m"), Money::default());
+        assert_eq!(total("monthly"), Money::default());
+        assert_eq!(total("weekly"), Money::default());
+
+        // the report's --show-annual column.
+        let annual = Period { count: 12, unit: Frequency::monthly };
+        let rows = summarise_split(&ledger, annual, annual, None, on, &config).rows;
+        assert_eq!(rows.iter().map(|row| row.amount).collect::<Vec<_>>(), vec![Money::from_dollars(-1200.0)]);
+
+        // in the month it happens, a month or a week holding it gets it in full too.
+        let on = NaiveDate::from_ymd_opt(2025, 5, 28).unwrap();
+        assert_eq!(summarise_split(&ledger, Period::from(Frequency::weekly), Period::from(Frequency::weekly), None, on, &config).total, Money::from_dollars(-1200.0));
+        let on = NaiveDate::from_ymd_opt(2025, 6, 20).unwrap();
+        assert_eq!(summarise_split(&ledger, Period::from(Frequency::monthly), Period::from(Frequency::monthly), None, on, &config).total, Money::from_dollars(-1200.0));
+    }
+
+    #[test]
+    fn income_sources_are_biggest_first_then_by_name()
+    {
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("income monthly rent 300 --category property"));
+        insert(&mut ledger, transaction("income monthly job 4000 --category salary"));
+        insert(&mut ledger, transaction("income monthly shares 300 --category dividends"));
+        insert(&mut ledger, transaction("income monthly gift 50"));
+
+        let summary = summarise(&ledger, Frequency::monthly, &Config::default());
+        let names: Vec<&str> = biggest_first(&summary.income_sources, ("(other)", summary.other_income)).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["salary", "dividends", "property", "(other)"]);
+    }
+
+    #[test]
+    fn amounts_too_big_to_hold_stop_at_the_biggest()
+    {
+        assert_eq!(Money::from_str("1e16"), Ok(Money { mills: i64::MAX }));
+        assert_eq!(Money::from_str("-1e30"), Ok(Money { mills: i64::MIN }));
+        assert_eq!(Money::from_str("inf"), Ok(Money { mills: i64::MAX }));
+        assert_eq!(Money::from_str("12.345"), Ok(Money { mills: 12_350 }));
+    }
+
+    #[test]
+    fn a_month_is_prorated_by_the_days_a_transaction_is_active()
+    {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let rent = transaction("expense monthly rent 1000 --start-date 2025-06-20 --until 2025-08-10");
+
+        assert_eq!(rent.month_fraction(date(2025, 5, 1)), Ratio::whole(0));
+        assert_eq!(rent.month_fraction(date(2025, 6, 1)), Ratio::new(11, 30));
+        assert_eq!(rent.month_fraction(date(2025, 7, 15)), Ratio::whole(1));
+        assert_eq!(rent.month_fraction(date(2025, 8, 31)), Ratio::new(10, 31));
+        assert_eq!(Money::from_dollars(1000.0).times(rent.month_fraction(date(2025, 6, 1))), Money { mills: 366_667 });
+
+        let gym = transaction("expense monthly gym 50");
+        assert_eq!(gym.month_fraction(date(2024, 2, 29)), Ratio::whole(1));
+    }
+
+    #[test]
+    fn groups_by_tag_ignore_case_and_put_untagged_last()
+    {
+        let rent = transaction("expense monthly rent 1200 --tag fixed --tag household");
+        let power = transaction("expense monthly power 160 --tag household --tag fixed --tag HOUSEHOLD");
+        let lunch = transaction("expense weekly lunch 60");
+        let all = [&rent, &power, &lunch];
+
+        let sections: Vec<(String, Vec<&str>)> = groups(all.iter().cloned(), GroupBy::tag, |t| t).into_iter()
+            .map(|(name, entries)| (name, entries.iter().map(|t| t.name.as_str()).collect()))
+            .collect();
+
+        assert_eq!(sections, vec![
+            ("fixed".to_string(), vec!["rent", "power"]),
+            ("HOUSEHOLD".to_string(), vec!["rent", "power"]),
+            ("(unassigned)".to_string(), vec!["lunch"]),
+        ]);
+    }
+
+    #[test]
+    fn report_per_takes_periods_and_payday()
+    {
+        assert_eq!(Per::from_str("payday"), Ok(Per::payday));
+        assert_eq!(Per::from_str("weekly"), Ok(Per::period(Period::from(Frequency::weekly))));
+        assert_eq!(Per::from_str("2w"), Ok(Per::period(Period { count: 2, unit: Frequency::weekly })));
+        assert!(Per::from_str("fortnight").is_err());
+    }
+
+    #[test]
+    fn periods_too_far_ahead_have_no_date()
+    {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        assert_eq!(Period::from(Frequency::monthly).after(today, 1), NaiveDate::from_ymd_opt(2025, 2, 28));
+        assert_eq!(Period::from(Frequency::yearly).after(today, u32::MAX), None);
+        assert_eq!(Period::from(Frequency::daily).after(today, u32::MAX), None);
+    }
+
+    #[test]
+    fn due_day_is_only_saved_when_it_is_set()
+    {
+        let saved = serde_json::to_value(transaction("expense monthly rent 1200")).unwrap();
+        assert!(saved.get("due_day").is_none());
+
+        let saved = serde_json::to_value(transaction("expense monthly rent 1200 --due-day 15")).unwrap();
+        assert_eq!(saved["due_day"], json!(15));
+    }
+
+    #[test]
+    fn my_share_of_expenses_is_rounded_like_the_rest()
+    {
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("expense monthly rent 1000.01 --share 50"));
+        insert(&mut ledger, transaction("expense monthly power 100.01 --share 50"));
+
+        let summary = summarise(&ledger, Frequency::monthly, &Config::default());
+        assert_eq!(summary.my_expenses, Money::from_dollars(500.01) + Money::from_dollars(50.01));
+
+        let fives = summarise_split(&ledger, Period::from(Frequency::monthly), Period::from(Frequency::monthly), Some(Money::from_dollars(5.0)), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), &Config::default());
+        assert_eq!(fives.my_expenses, Money::from_dollars(550.0));
+    }
+
+    #[test]
+    fn init_keeps_a_ledger_with_something_in_it()
+    {
+        let _lock = data_dir_lock();
+
+        assert!(init(false).is_ok());
+        let mut ledger = Ledger::new();
+        insert(&mut ledger, transaction("expense monthly coffee 5"));
+        save_current_ledger(ledger).unwrap();
+
+        assert!(matches!(init(false), Err(Error::LedgerIsNotEmpty(1))));
+        assert_eq!(load_current_ledger().ok().map(|ledger| ledger.len()), Some(1));
+    }
+
+    #[test]
+    fn undoing_rm_takes_it_out_of_the_trash()
+    {
+        let _lock = data_dir_lock();
+
+        init(false).unwrap();
+        add(transaction("expense weekly coffee 20")).unwrap();
+        add(transaction("expense monthly rent 1200")).unwrap();
+
+        rm(RmCommand::from_clap(&RmCommand::clap().get_matches_from(args("rm coffee")))).unwrap();
+        assert_eq!(load_trash().unwrap().len(), 1);
+
+        undo().unwrap();
+        let ledger = load_current_ledger().unwrap();
+        assert_eq!(ledger.len(), 2);
+        assert!(ledger.values().any(|t| t.name == "coffee"));
+        assert!(load_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undoing_a_restore_puts_it_back_in_the_trash()
+    {
+        let _lock = data_dir_lock();
+
+        init(false).unwrap();
+        add(transaction("expense weekly coffee 20")).unwrap();
+        rm(RmCommand::from_clap(&RmCommand::clap().get_matches_from(args("rm coffee")))).unwrap();
+
+        trash(TrashCommand::restore { name: "coffee".to_string() }).unwrap();
+        assert_eq!(load_current_ledger().unwrap().len(), 1);
+        assert!(load_trash().unwrap().is_empty());
+
+        undo().unwrap();
+        assert!(load_current_ledger().unwrap().is_empty());
+        let trash = load_trash().unwrap();
+        assert_eq!(trash.values().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["coffee"]);
+    }
+
+    #[test]
+    fn init_keeps_a_ledger_it_cannot_read()
+    {
+        let _lock = data_dir_lock();
+
+        let path = get_path(".current_data").unwrap();
+        let unreadable = r#"{ "version": 9, "transactions": {} }"#;
+        fs::write(&path, unreadable).unwrap();
+
+        assert!(matches!(init(false), Err(Error::LedgerIsUnreadable)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), unreadable);
+
+        assert!(init(true).is_ok());
+        assert_eq!(load_current_ledger().ok().map(|ledger| ledger.len()), Some(0));
+    }
+
+    #[test]
+    fn strict_loading_knows_every_field_and_nothing_else()
+    {
+        let _lock = data_dir_lock();
+
+        let example = serde_json::to_value(example_transaction()).unwrap();
+        let mut stored: Vec<&str> = example.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut known = transaction_fields().to_vec();
+        stored.sort();
+        known.sort();
+        assert_eq!(stored, known);
+
+        let mut typo = example.clone();
+        typo["tag"] = json!(["fixed"]);
+        let ledger = json!({ "version": LEDGER_VERSION, "transactions": { "1": typo } });
+        fs::write(get_path(".current_data").unwrap(), ledger.to_string()).unwrap();
+
+        env::set_var("PFR_STRICT", "1");
+        let strict = read_ledger(".current_data");
+        env::remove_var("PFR_STRICT");
+
+        assert!(matches!(strict, Err(Error::UnknownField(ref t, ref f)) if t == "1" && f == "tag"));
+        assert_eq!(read_ledger(".current_data").ok().map(|(ledger, _)| ledger.len()), Some(1));
+    }
+
+    #[test]
+    fn only_a_bare_add_runs_the_wizard()
+    {
+        assert!(is_bare_add(&args("pfr add")));
+        assert!(is_bare_add(&args("pfr --json --data-dir /tmp/add add")));
+        assert!(is_bare_add(&args("pfr --locale=en-GB add")));
+
+        assert!(!is_bare_add(&args("pfr")));
+        assert!(!is_bare_add(&args("pfr show add")));
+        assert!(!is_bare_add(&args("pfr rm add")));
+        assert!(!is_bare_add(&args("pfr edit add --amount 5")));
+        assert!(!is_bare_add(&args("pfr add expense")));
+        assert!(!is_bare_add(&args("pfr --data-dir add")));
+    }
+}